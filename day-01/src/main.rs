@@ -1,74 +1,594 @@
-use std::collections::HashMap;
-use std::{env, fs};
+use std::{env, fmt, fs};
 
 // Problem 1: Fixing your expense report.
 // https://adventofcode.com/2020/day/1
 // Given a list of numbers, find the numbers that sum to 2020, and return what their product would be.
 
-const TARGET_SUM: i32 = 2020;
+// Hint: K Sum
+// Find k numbers that sum to a target, which generalizes Two Sum (Part 1) and 3Sum (Part 1 Extended).
+// * Sort the entries, remembering the index each entry had in the input.
+// * For k = 2, move two pointers inwards from both ends of the sorted entries,
+//   the low pointer moves up when the sum is too small, and the high pointer moves down when it's too large.
+// * For k > 2, fix each entry in turn as the smallest one, and find the remaining k - 1 entries after it
+//   that sum to target - entry. Entries for which the smallest (or largest) possible sum already misses the target are skipped.
 
-fn main() {
-    let args = env::args().collect::<Vec<String>>();
-    let filepath = &args[1];
+const TARGET_SUM: i64 = 2020;
 
-    println!("--- Part 1 ---");
-    let mut entries: Vec<i32> = Vec::new();
-    let mut map: HashMap<i32, usize> = HashMap::new();
-    let input = fs::read_to_string(filepath).unwrap();
-    for line in input.lines() {
-        let entry = line.parse::<i32>().unwrap();
-        entries.push(entry);
-    }
-
-    // Hint: Two Sum
-    // Given an array of numbers, find its pair that equals a target
-    for (index, entry) in entries.iter().enumerate() {
-        let complement = TARGET_SUM - entry;
-        if map.contains_key(&complement) {
-            let chosen_one_index = map.get(&complement).unwrap();
-            let chosen_two_index = &index;
-
-            let chosen_one = entries.get(*chosen_one_index as usize).unwrap();
-            let chosen_two = entries.get(*chosen_two_index as usize).unwrap();
-
-            println!("2 entries that sum to 2020: {}, {}", chosen_one, chosen_two);
-            println!("Product Of two entries: {}", chosen_one * chosen_two);
-        } else {
-            map.insert(*entry, index);
-        }
+/// Entries that sum to the target, in ascending order of value, along with their indices in the input.
+#[derive(Debug, PartialEq)]
+struct KSum {
+    indices: Vec<usize>,
+    values: Vec<i32>,
+}
+
+impl KSum {
+    /// Returns the product of the values, or None if it overflows.
+    fn product(&self) -> Option<i128> {
+        self.values
+            .iter()
+            .try_fold(1i128, |acc, v| acc.checked_mul(*v as i128))
     }
+}
 
-    println!("");
-    println!("-- Part 1 Extended --");
-    // Hint: 3Sum
-    // Find three numbers that sum to target
-    entries.sort();
+/// Entries sorted by value, each along with its index in the input.
+type SortedEntries = Vec<(usize, i32)>;
 
-    for (i, entry) in entries.iter().enumerate() {
-        let mut low = i + 1;
-        let mut high = entries.len() - 1;
+fn sort_entries(entries: &[i32]) -> SortedEntries {
+    let mut sorted: SortedEntries = entries.iter().cloned().enumerate().collect();
+    sorted.sort_by_key(|(_, value)| *value);
+    sorted
+}
+
+fn sum_of(entries: &[(usize, i32)]) -> i64 {
+    entries.iter().map(|(_, value)| *value as i64).sum()
+}
+
+/// Visits every distinct combination of k entries in `sorted[start..]` that sums to the target,
+/// by calling `visit` with the positions of the entries in `sorted`. Combinations are distinct by their values,
+/// so equal entries are only tried once at every level. The search stops as soon as `visit` returns false.
+/// Returns false if the search was stopped.
+fn visit_k_sums(
+    sorted: &[(usize, i32)],
+    start: usize,
+    k: usize,
+    target: i64,
+    chosen: &mut Vec<usize>,
+    visit: &mut dyn FnMut(&[usize]) -> bool,
+) -> bool {
+    let remaining = &sorted[start..];
+    if k == 0 {
+        return target != 0 || visit(chosen);
+    }
+    if remaining.len() < k {
+        return true;
+    }
+
+    // Even the k smallest / largest entries can't reach the target.
+    if sum_of(&remaining[..k]) > target || sum_of(&remaining[remaining.len() - k..]) < target {
+        return true;
+    }
+
+    if k == 1 {
+        // binary_search may land on any of several equal entries, so look for the first one.
+        let position = remaining.partition_point(|(_, value)| (*value as i64) < target);
+        match remaining.get(position) {
+            Some((_, value)) if *value as i64 == target => {}
+            _ => return true,
+        }
 
+        chosen.push(start + position);
+        let should_continue = visit(chosen);
+        chosen.pop();
+        return should_continue;
+    }
+
+    if k == 2 {
+        let mut low = start;
+        let mut high = sorted.len() - 1;
         while low < high {
-            let current_sum = &entries[low] + &entries[high] + entry;
-            // since there's only one such entry based on the question, we can break here.
-            // otherwise we'd typically push these into a Vec<u8> | HashSet<u8> to deal with duplicates.
-            if current_sum == TARGET_SUM {
-                println!(
-                    "3 Entries that sum to 2020: {}, {}, {}",
-                    &entries[low], &entries[high], entry
-                );
-
-                println!(
-                    "Product of 3 Entries: {}",
-                    &entries[low] * &entries[high] * entry
-                );
-
-                break;
-            } else if current_sum < TARGET_SUM {
+            let current_sum = sorted[low].1 as i64 + sorted[high].1 as i64;
+            if current_sum == target {
+                chosen.push(low);
+                chosen.push(high);
+                let should_continue = visit(chosen);
+                chosen.truncate(chosen.len() - 2);
+                if !should_continue {
+                    return false;
+                }
+
+                // skip over entries equal to the ones we just used, so that the pair isn't repeated
+                let (low_value, high_value) = (sorted[low].1, sorted[high].1);
+                while low < high && sorted[low].1 == low_value {
+                    low += 1;
+                }
+                while low < high && sorted[high].1 == high_value {
+                    high -= 1;
+                }
+            } else if current_sum < target {
                 low += 1;
             } else {
                 high -= 1;
             }
         }
+
+        return true;
+    }
+
+    for i in start..=sorted.len() - k {
+        if i > start && sorted[i].1 == sorted[i - 1].1 {
+            continue;
+        }
+
+        chosen.push(i);
+        let should_continue = visit_k_sums(
+            sorted,
+            i + 1,
+            k - 1,
+            target - sorted[i].1 as i64,
+            chosen,
+            visit,
+        );
+        chosen.pop();
+        if !should_continue {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn to_k_sum(sorted: &[(usize, i32)], chosen: &[usize]) -> KSum {
+    KSum {
+        indices: chosen.iter().map(|&position| sorted[position].0).collect(),
+        values: chosen.iter().map(|&position| sorted[position].1).collect(),
+    }
+}
+
+/// Finds k entries that sum to the target. Every entry can be used at most once.
+fn find_k_sum(entries: &[i32], k: usize, target: i64) -> Option<KSum> {
+    let sorted = sort_entries(entries);
+    let mut result = None;
+
+    visit_k_sums(
+        &sorted,
+        0,
+        k,
+        target,
+        &mut Vec::with_capacity(k),
+        &mut |chosen| {
+            result = Some(to_k_sum(&sorted, chosen));
+            false
+        },
+    );
+
+    result
+}
+
+/// Finds every distinct combination of k entries that sums to the target.
+/// Combinations with the same values are only returned once, even if they come from different entries.
+fn find_all_k_sums(entries: &[i32], k: usize, target: i64) -> Vec<KSum> {
+    let sorted = sort_entries(entries);
+    let mut results = Vec::new();
+
+    visit_k_sums(
+        &sorted,
+        0,
+        k,
+        target,
+        &mut Vec::with_capacity(k),
+        &mut |chosen| {
+            results.push(to_k_sum(&sorted, chosen));
+            true
+        },
+    );
+
+    results
+}
+
+/// Counts the distinct combinations of k entries that sum to the target, without collecting them.
+fn count_k_sums(entries: &[i32], k: usize, target: i64) -> u64 {
+    let sorted = sort_entries(entries);
+    let mut count = 0;
+
+    visit_k_sums(
+        &sorted,
+        0,
+        k,
+        target,
+        &mut Vec::with_capacity(k),
+        &mut |_| {
+            count += 1;
+            true
+        },
+    );
+
+    count
+}
+
+// Subset Sum
+// Find any number of entries that sum to a target, using dynamic programming over a bitset of reachable sums.
+// * Every sum of a subset lies between the sum of all negative entries and the sum of all positive entries,
+//   so reachable sums are stored as bits, offset by the sum of the negative entries.
+// * Adding an entry makes every reachable sum + entry reachable, i.e. row[i + 1] = row[i] | (row[i] << entry).
+// * Keeping every row lets us walk back from the target: if the sum wasn't reachable without entry i, entry i was used.
+
+const MAX_TARGET_MAGNITUDE: i64 = 1 << 24;
+const MAX_TABLE_BITS: u64 = 1 << 30;
+
+#[derive(Debug, PartialEq)]
+enum SubsetSumError {
+    TargetTooLarge { target: i64 },
+    TableTooLarge { rows: usize, sums: u64 },
+}
+
+impl fmt::Display for SubsetSumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SubsetSumError::TargetTooLarge { target } => write!(
+                f,
+                "Target {} is too large, it must be within ±{}",
+                target, MAX_TARGET_MAGNITUDE
+            ),
+            SubsetSumError::TableTooLarge { rows, sums } => write!(
+                f,
+                "Subset sum table of {} entries x {} sums exceeds the limit of {} bits",
+                rows, sums, MAX_TABLE_BITS
+            ),
+        }
+    }
+}
+
+/// A set of sums, stored as one bit per sum.
+#[derive(Clone)]
+struct Bitset {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitset {
+    fn new(len: usize) -> Self {
+        Bitset {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    fn get(&self, position: usize) -> bool {
+        self.words[position / 64] & (1 << (position % 64)) != 0
+    }
+
+    fn set(&mut self, position: usize) {
+        self.words[position / 64] |= 1 << (position % 64);
+    }
+
+    /// Returns self | (self shifted by `shift` bits), dropping bits that move out of range.
+    fn or_shifted(&self, shift: i64) -> Bitset {
+        let mut result = self.clone();
+        let (word_shift, bit_shift) = (
+            (shift.unsigned_abs() / 64) as usize,
+            shift.unsigned_abs() % 64,
+        );
+        let word_count = self.words.len();
+
+        for i in 0..word_count {
+            let shifted = if shift >= 0 {
+                // bits move towards higher positions
+                let low = i
+                    .checked_sub(word_shift)
+                    .map_or(0, |j| self.words[j] << bit_shift);
+                let carry = match i.checked_sub(word_shift + 1) {
+                    Some(j) if bit_shift > 0 => self.words[j] >> (64 - bit_shift),
+                    _ => 0,
+                };
+                low | carry
+            } else {
+                // bits move towards lower positions
+                let high = self.words.get(i + word_shift).map_or(0, |w| w >> bit_shift);
+                let carry = match self.words.get(i + word_shift + 1) {
+                    Some(w) if bit_shift > 0 => w << (64 - bit_shift),
+                    _ => 0,
+                };
+                high | carry
+            };
+            result.words[i] |= shifted;
+        }
+
+        // clear the bits past the end, which the shift may have set
+        let used_bits = self.len % 64;
+        if used_bits > 0 {
+            result.words[word_count - 1] &= (1 << used_bits) - 1;
+        }
+
+        result
+    }
+}
+
+/// Finds a subset of any number of entries that sums to the target.
+/// The empty subset is a valid answer for a target of 0.
+fn find_subset_sum(entries: &[i32], target: i64) -> Result<Option<KSum>, SubsetSumError> {
+    if target.abs() > MAX_TARGET_MAGNITUDE {
+        return Err(SubsetSumError::TargetTooLarge { target });
+    }
+
+    let lowest: i64 = entries.iter().filter(|e| **e < 0).map(|e| *e as i64).sum();
+    let highest: i64 = entries.iter().filter(|e| **e > 0).map(|e| *e as i64).sum();
+    if target < lowest || target > highest {
+        return Ok(None);
+    }
+
+    let sums = (highest - lowest + 1) as u64;
+    let rows = entries.len() + 1;
+    if sums.saturating_mul(rows as u64) > MAX_TABLE_BITS {
+        return Err(SubsetSumError::TableTooLarge { rows, sums });
+    }
+
+    // rows[i] holds the sums reachable using the first i entries, with sum s stored at bit s - lowest.
+    let mut table: Vec<Bitset> = Vec::with_capacity(rows);
+    let mut reachable = Bitset::new(sums as usize);
+    reachable.set(-lowest as usize);
+    for entry in entries {
+        let next = reachable.or_shifted(*entry as i64);
+        table.push(reachable);
+        reachable = next;
+    }
+
+    let mut position = (target - lowest) as usize;
+    if !reachable.get(position) {
+        return Ok(None);
+    }
+
+    // Walk back through the rows to find the entries that were used.
+    let mut indices = Vec::new();
+    for (index, row) in table.iter().enumerate().rev() {
+        if !row.get(position) {
+            indices.push(index);
+            position = (position as i64 - entries[index] as i64) as usize;
+        }
+    }
+    indices.reverse();
+
+    Ok(Some(KSum {
+        values: indices.iter().map(|&i| entries[i]).collect(),
+        indices,
+    }))
+}
+
+/// Returns the value passed for a flag such as `--target 2020`.
+fn get_flag<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
+    let position = args.iter().position(|arg| arg == flag)?;
+    let value = args
+        .get(position + 1)
+        .unwrap_or_else(|| panic!("Missing value for {}", flag));
+
+    match value.parse::<T>() {
+        Ok(value) => Some(value),
+        Err(_) => panic!("Invalid value for {}: {}", flag, value),
+    }
+}
+
+fn print_k_sum(k: usize, target: i64, k_sum: &KSum) {
+    println!(
+        "{} entries that sum to {}: {:?} (at lines {:?})",
+        k,
+        target,
+        k_sum.values,
+        k_sum.indices.iter().map(|i| i + 1).collect::<Vec<usize>>()
+    );
+    match k_sum.product() {
+        Some(product) => println!("Product of {} entries: {}", k, product),
+        None => println!("Product of {} entries is too large", k),
+    }
+}
+
+fn solve_subset_sum(entries: &[i32], target: i64) {
+    match find_subset_sum(entries, target) {
+        Ok(Some(subset)) => print_k_sum(subset.values.len(), target, &subset),
+        Ok(None) => println!("No subset of entries sums to {}", target),
+        Err(e) => eprintln!("Error: {}", e),
+    }
+}
+
+fn solve(entries: &[i32], k: usize, target: i64, args: &[String]) {
+    if args.iter().any(|arg| arg == "--count") {
+        let count = count_k_sums(entries, k, target);
+        println!("{} distinct sets of {} entries sum to {}", count, k, target);
+        return;
+    }
+
+    let results = if args.iter().any(|arg| arg == "--all") {
+        find_all_k_sums(entries, k, target)
+    } else {
+        find_k_sum(entries, k, target).into_iter().collect()
+    };
+
+    if results.is_empty() {
+        println!("No {} entries sum to {}", k, target);
+    }
+
+    for k_sum in results.iter() {
+        print_k_sum(k, target, k_sum);
+    }
+}
+
+fn main() {
+    // Usage: day-01 <input> [--target <sum>] [--k <number of entries>] [--all | --count] [--subset]
+    let args = env::args().collect::<Vec<String>>();
+    let filepath = &args[1];
+    let target = get_flag::<i64>(&args, "--target").unwrap_or(TARGET_SUM);
+
+    let input = fs::read_to_string(filepath).unwrap();
+    let entries: Vec<i32> = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.trim().parse::<i32>().unwrap())
+        .collect();
+
+    if args.iter().any(|arg| arg == "--subset") {
+        solve_subset_sum(&entries, target);
+        return;
+    }
+
+    if let Some(k) = get_flag::<usize>(&args, "--k") {
+        solve(&entries, k, target, &args);
+        return;
+    }
+
+    println!("--- Part 1 ---");
+    solve(&entries, 2, target, &args);
+
+    println!();
+    println!("-- Part 1 Extended --");
+    solve(&entries, 3, target, &args);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRIES: [i32; 6] = [1721, 979, 366, 299, 675, 1456];
+
+    #[test]
+    fn should_find_two_sum() {
+        let result = find_k_sum(&ENTRIES, 2, 2020).unwrap();
+        assert_eq!(result.values, vec![299, 1721]);
+        assert_eq!(result.indices, vec![3, 0]);
+        assert_eq!(result.product(), Some(514579));
+    }
+
+    #[test]
+    fn should_find_three_sum() {
+        let result = find_k_sum(&ENTRIES, 3, 2020).unwrap();
+        assert_eq!(result.values, vec![366, 675, 979]);
+        assert_eq!(result.indices, vec![2, 4, 1]);
+        assert_eq!(result.product(), Some(241861950));
+    }
+
+    #[test]
+    fn should_find_k_sum_for_any_k_and_target() {
+        let result = find_k_sum(&ENTRIES, 4, 299 + 366 + 675 + 1456).unwrap();
+        assert_eq!(result.values, vec![299, 366, 675, 1456]);
+
+        assert_eq!(find_k_sum(&ENTRIES, 1, 979).unwrap().indices, vec![1]);
+        assert_eq!(find_k_sum(&ENTRIES, 6, 5496).unwrap().values.len(), 6);
+        assert_eq!(find_k_sum(&ENTRIES, 0, 0).unwrap().values, vec![]);
+
+        assert_eq!(find_k_sum(&ENTRIES, 2, 1), None);
+        assert_eq!(find_k_sum(&ENTRIES, 7, 5496), None);
+    }
+
+    #[test]
+    fn should_use_each_entry_at_most_once() {
+        assert_eq!(find_k_sum(&[1010, 1, 2], 2, 2020), None);
+        assert_eq!(
+            find_k_sum(&[1010, 1, 1010], 2, 2020).unwrap().indices,
+            vec![0, 2]
+        );
+        assert_eq!(
+            find_k_sum(&[-5, 10, 3, -2], 3, 3).unwrap().values,
+            vec![-5, -2, 10]
+        );
+    }
+
+    #[test]
+    fn should_find_all_distinct_k_sums() {
+        let entries = [1, 5, 3, 3, 5, 1, 2, 4, 4];
+        let values: Vec<Vec<i32>> = find_all_k_sums(&entries, 2, 6)
+            .into_iter()
+            .map(|k_sum| k_sum.values)
+            .collect();
+        assert_eq!(values, vec![vec![1, 5], vec![2, 4], vec![3, 3]]);
+
+        let values: Vec<Vec<i32>> = find_all_k_sums(&entries, 3, 9)
+            .into_iter()
+            .map(|k_sum| k_sum.values)
+            .collect();
+        assert_eq!(values, vec![vec![1, 3, 5], vec![1, 4, 4], vec![2, 3, 4]]);
+    }
+
+    #[test]
+    fn should_handle_duplicate_entries() {
+        // 1010 can only be used twice if it appears twice.
+        assert!(find_all_k_sums(&[1010, 1, 2], 2, 2020).is_empty());
+
+        let results = find_all_k_sums(&[1010, 7, 1010, 1010], 2, 2020);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].values, vec![1010, 1010]);
+        assert_eq!(results[0].indices, vec![0, 3]);
+
+        assert_eq!(find_all_k_sums(&[2, 2, 2, 2], 3, 6).len(), 1);
+        assert_eq!(find_all_k_sums(&[2, 2, 2, 2], 1, 2).len(), 1);
+    }
+
+    #[test]
+    fn should_count_k_sums() {
+        assert_eq!(count_k_sums(&ENTRIES, 2, 2020), 1);
+        assert_eq!(count_k_sums(&[1, 5, 3, 3, 5, 1, 2, 4, 4], 3, 9), 3);
+
+        // Every pair of distinct values 1..=100 summing to 101
+        let entries: Vec<i32> = (1..=100).collect();
+        assert_eq!(count_k_sums(&entries, 2, 101), 50);
+        assert_eq!(count_k_sums(&entries, 3, 3), 0);
+    }
+
+    #[test]
+    fn should_find_subset_sum() {
+        let subset = find_subset_sum(&ENTRIES, 1721 + 366 + 1456)
+            .unwrap()
+            .unwrap();
+        assert_eq!(subset.values.iter().map(|v| *v as i64).sum::<i64>(), 3543);
+        for (index, value) in subset.indices.iter().zip(subset.values.iter()) {
+            assert_eq!(ENTRIES[*index], *value);
+        }
+
+        assert_eq!(
+            find_subset_sum(&ENTRIES, 5496).unwrap().unwrap().indices,
+            vec![0, 1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            find_subset_sum(&ENTRIES, 0).unwrap().unwrap().indices,
+            vec![]
+        );
+        assert_eq!(find_subset_sum(&ENTRIES, 1).unwrap(), None);
+        assert_eq!(find_subset_sum(&ENTRIES, 5497).unwrap(), None);
+    }
+
+    #[test]
+    fn should_find_subset_sum_with_negative_entries() {
+        let entries = [-7, 12, -3, 5, 100];
+        let subset = find_subset_sum(&entries, -5).unwrap().unwrap();
+        assert_eq!(subset.values, vec![-7, -3, 5]);
+
+        let subset = find_subset_sum(&entries, 95).unwrap().unwrap();
+        assert_eq!(subset.values.iter().sum::<i32>(), 95);
+        assert_eq!(find_subset_sum(&entries, -11).unwrap(), None);
+    }
+
+    #[test]
+    fn should_shift_bitset_across_words() {
+        let mut bitset = Bitset::new(200);
+        bitset.set(3);
+        bitset.set(150);
+
+        let shifted = bitset.or_shifted(70);
+        let set: Vec<usize> = (0..200).filter(|i| shifted.get(*i)).collect();
+        assert_eq!(set, vec![3, 73, 150]);
+
+        let shifted = bitset.or_shifted(-130);
+        let set: Vec<usize> = (0..200).filter(|i| shifted.get(*i)).collect();
+        assert_eq!(set, vec![3, 20, 150]);
+    }
+
+    #[test]
+    fn should_reject_large_subset_sums() {
+        assert_eq!(
+            find_subset_sum(&ENTRIES, 1 << 40),
+            Err(SubsetSumError::TargetTooLarge { target: 1 << 40 })
+        );
+
+        let entries = vec![i32::MAX; 1000];
+        assert!(matches!(
+            find_subset_sum(&entries, 2020),
+            Err(SubsetSumError::TableTooLarge { .. })
+        ));
     }
 }
@@ -1,19 +1,142 @@
-use std::collections::HashMap;
-use std::{env, fs};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::{env, fmt, fs};
+
+/// The reason a password doesn't satisfy a policy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Failure {
+    MissingLetter {
+        letter: char,
+    },
+    BelowMin {
+        letter: char,
+        count: usize,
+        min: usize,
+    },
+    AboveMax {
+        letter: char,
+        count: usize,
+        max: usize,
+    },
+    /// Positions are 1-indexed, as they are in the input.
+    BothPositions {
+        letter: char,
+        first: usize,
+        last: usize,
+    },
+    NeitherPosition {
+        letter: char,
+        first: usize,
+        last: usize,
+    },
+    NoRegexMatch {
+        pattern: String,
+    },
+    ForbiddenCharacter {
+        character: char,
+    },
+    TooFewDistinct {
+        count: usize,
+        min: usize,
+    },
+    /// None of the policies of an `or` were satisfied.
+    NoneSatisfied(Vec<Failure>),
+    /// The policy of a `not` was satisfied.
+    Negated,
+}
+
+impl Failure {
+    /// Returns the name of the kind of failure, used to group failures in the report.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Failure::MissingLetter { .. } => "missing letter",
+            Failure::BelowMin { .. } => "below min",
+            Failure::AboveMax { .. } => "above max",
+            Failure::BothPositions { .. } => "both positions",
+            Failure::NeitherPosition { .. } => "neither position",
+            Failure::NoRegexMatch { .. } => "no regex match",
+            Failure::ForbiddenCharacter { .. } => "forbidden character",
+            Failure::TooFewDistinct { .. } => "too few distinct characters",
+            Failure::NoneSatisfied(_) => "no alternative satisfied",
+            Failure::Negated => "negated policy satisfied",
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::MissingLetter { letter } => write!(f, "letter '{}' is missing", letter),
+            Failure::BelowMin { letter, count, min } => write!(
+                f,
+                "letter '{}' occurs {} times, below the min of {}",
+                letter, count, min
+            ),
+            Failure::AboveMax { letter, count, max } => write!(
+                f,
+                "letter '{}' occurs {} times, above the max of {}",
+                letter, count, max
+            ),
+            Failure::BothPositions {
+                letter,
+                first,
+                last,
+            } => write!(
+                f,
+                "letter '{}' is at both positions {} and {}",
+                letter, first, last
+            ),
+            Failure::NeitherPosition {
+                letter,
+                first,
+                last,
+            } => write!(
+                f,
+                "letter '{}' is at neither position {} nor {}",
+                letter, first, last
+            ),
+            Failure::NoRegexMatch { pattern } => write!(f, "doesn't match /{}/", pattern),
+            Failure::ForbiddenCharacter { character } => {
+                write!(f, "contains forbidden character '{}'", character)
+            }
+            Failure::TooFewDistinct { count, min } => write!(
+                f,
+                "has {} distinct characters, below the min of {}",
+                count, min
+            ),
+            Failure::NoneSatisfied(failures) => write!(
+                f,
+                "none of the alternatives are satisfied ({})",
+                failures
+                    .iter()
+                    .map(|failure| failure.to_string())
+                    .collect::<Vec<String>>()
+                    .join("; ")
+            ),
+            Failure::Negated => write!(f, "satisfies a policy it shouldn't"),
+        }
+    }
+}
 
 pub trait ValidatePassword {
-    fn validate(&self, password: &str) -> bool;
+    /// Checks the password against the policy, returning the reason it fails.
+    fn check(&self, password: &str) -> Result<(), Failure>;
+
+    fn validate(&self, password: &str) -> bool {
+        self.check(password).is_ok()
+    }
 }
 
 struct OldPasswordPolicy {
-    min: i32,
-    max: i32,
+    min: usize,
+    max: usize,
     letter: char,
 }
 
 impl ValidatePassword for OldPasswordPolicy {
-    fn validate(&self, password: &str) -> bool {
-        let mut occurences: HashMap<char, i32> = HashMap::new();
+    fn check(&self, password: &str) -> Result<(), Failure> {
+        let mut occurences: HashMap<char, usize> = HashMap::new();
 
         for character in password.chars() {
             // increments entry by 1, if exists else inserts 1.
@@ -23,20 +146,21 @@ impl ValidatePassword for OldPasswordPolicy {
                 .or_insert(1);
         }
 
-        let has_letter = occurences.contains_key(&self.letter);
-
-        if !has_letter {
-            return false;
-        } else {
-            let count = occurences.get(&self.letter).unwrap();
-            if count < &self.min {
-                return false;
-            }
-            if count > &self.max {
-                return false;
-            }
+        let letter = self.letter;
+        match occurences.get(&letter) {
+            None => Err(Failure::MissingLetter { letter }),
+            Some(&count) if count < self.min => Err(Failure::BelowMin {
+                letter,
+                count,
+                min: self.min,
+            }),
+            Some(&count) if count > self.max => Err(Failure::AboveMax {
+                letter,
+                count,
+                max: self.max,
+            }),
+            Some(_) => Ok(()),
         }
-        true
     }
 }
 
@@ -47,132 +171,1058 @@ struct NewPasswordPolicy {
 }
 
 impl ValidatePassword for NewPasswordPolicy {
-    fn validate(&self, password: &str) -> bool {
-        let mut has_char_at_first_position = false;
-        let mut has_char_at_last_position = false;
+    fn check(&self, password: &str) -> Result<(), Failure> {
+        let has_char_at_first_position =
+            password.chars().nth(self.first_position) == Some(self.letter);
+        let has_char_at_last_position =
+            password.chars().nth(self.last_position) == Some(self.letter);
+
+        let (letter, first, last) = (self.letter, self.first_position + 1, self.last_position + 1);
+
+        // exactly one of the positions should have the letter
+        match (has_char_at_first_position, has_char_at_last_position) {
+            (true, true) => Err(Failure::BothPositions {
+                letter,
+                first,
+                last,
+            }),
+            (false, false) => Err(Failure::NeitherPosition {
+                letter,
+                first,
+                last,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The password has to match the regular expression.
+struct RegexPasswordPolicy {
+    regex: Regex,
+}
+
+impl ValidatePassword for RegexPasswordPolicy {
+    fn check(&self, password: &str) -> Result<(), Failure> {
+        if self.regex.is_match(password) {
+            return Ok(());
+        }
 
-        match password.chars().nth(self.first_position) {
-            Some(char_at_first_position) => {
-                has_char_at_first_position = char_at_first_position == self.letter;
+        Err(Failure::NoRegexMatch {
+            pattern: self.regex.as_str().to_owned(),
+        })
+    }
+}
+
+/// The password can't contain any of the characters.
+struct ForbiddenCharactersPolicy {
+    characters: HashSet<char>,
+}
+
+impl ValidatePassword for ForbiddenCharactersPolicy {
+    fn check(&self, password: &str) -> Result<(), Failure> {
+        match password.chars().find(|c| self.characters.contains(c)) {
+            Some(character) => Err(Failure::ForbiddenCharacter { character }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The password has to contain at least `min` different characters.
+struct MinDistinctCharactersPolicy {
+    min: usize,
+}
+
+impl ValidatePassword for MinDistinctCharactersPolicy {
+    fn check(&self, password: &str) -> Result<(), Failure> {
+        let count = password.chars().collect::<HashSet<char>>().len();
+        if count < self.min {
+            return Err(Failure::TooFewDistinct {
+                count,
+                min: self.min,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Fails with the failure of the first policy that isn't satisfied.
+struct AndPolicy {
+    policies: Vec<Box<dyn ValidatePassword>>,
+}
+
+impl ValidatePassword for AndPolicy {
+    fn check(&self, password: &str) -> Result<(), Failure> {
+        self.policies.iter().try_for_each(|p| p.check(password))
+    }
+}
+
+struct OrPolicy {
+    policies: Vec<Box<dyn ValidatePassword>>,
+}
+
+impl ValidatePassword for OrPolicy {
+    fn check(&self, password: &str) -> Result<(), Failure> {
+        let mut failures = Vec::new();
+        for policy in self.policies.iter() {
+            match policy.check(password) {
+                Ok(()) => return Ok(()),
+                Err(failure) => failures.push(failure),
             }
-            None => {}
         }
 
-        match password.chars().nth(self.last_position) {
-            Some(char_at_last_position) => {
-                has_char_at_last_position = char_at_last_position == self.letter;
+        Err(Failure::NoneSatisfied(failures))
+    }
+}
+
+struct NotPolicy {
+    policy: Box<dyn ValidatePassword>,
+}
+
+impl ValidatePassword for NotPolicy {
+    fn check(&self, password: &str) -> Result<(), Failure> {
+        match self.policy.check(password) {
+            Ok(()) => Err(Failure::Negated),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+// -- Policy Registry --
+
+// Policies are built from a spec, which is the name of the policy followed by its parameters:
+//   old 1-3 a              the letter `a` occurs 1 to 3 times
+//   new 1-3 a              the letter `a` is at exactly one of the positions 1 and 3
+//   regex ^[a-z]+$         the password matches the regular expression
+//   forbidden xyz          the password contains none of `x`, `y` or `z`
+//   distinct 4             the password has at least 4 different characters
+//   and(spec, spec, ...)   all / any of the policies are valid
+//   or(spec, spec, ...)
+//   not(spec)              the policy is invalid
+// Combinators take other specs as parameters, separated by commas that aren't nested in brackets.
+
+type PolicyBuilder = fn(&str, &PolicyRegistry) -> Result<Box<dyn ValidatePassword>, String>;
+
+struct PolicyRegistry {
+    builders: HashMap<String, PolicyBuilder>,
+}
+
+impl Default for PolicyRegistry {
+    fn default() -> Self {
+        let mut registry = PolicyRegistry {
+            builders: HashMap::new(),
+        };
+
+        registry.register("old", build_old_policy);
+        registry.register("new", build_new_policy);
+        registry.register("regex", build_regex_policy);
+        registry.register("forbidden", build_forbidden_characters_policy);
+        registry.register("distinct", build_min_distinct_characters_policy);
+        registry.register("and", build_and_policy);
+        registry.register("or", build_or_policy);
+        registry.register("not", build_not_policy);
+        registry
+    }
+}
+
+impl PolicyRegistry {
+    fn register(&mut self, name: &str, builder: PolicyBuilder) {
+        self.builders.insert(name.to_owned(), builder);
+    }
+
+    /// Builds a policy from a spec such as `old 1-3 a` or `and(old 1-3 a, not(forbidden xyz))`.
+    fn build(&self, spec: &str) -> Result<Box<dyn ValidatePassword>, String> {
+        let spec = spec.trim();
+        let name_end = spec
+            .find(|c: char| c == '(' || c.is_whitespace())
+            .unwrap_or(spec.len());
+        let (name, params) = spec.split_at(name_end);
+
+        match self.builders.get(name) {
+            Some(builder) => builder(params.trim(), self),
+            None => Err(format!("Unknown policy {:?}", name)),
+        }
+    }
+}
+
+/// Builds every distinct spec once, so that e.g. a regex isn't compiled again for every line it's used on.
+struct PolicyCache<'a> {
+    registry: &'a PolicyRegistry,
+    policies: HashMap<String, Box<dyn ValidatePassword>>,
+}
+
+impl<'a> PolicyCache<'a> {
+    fn new(registry: &'a PolicyRegistry) -> Self {
+        PolicyCache {
+            registry,
+            policies: HashMap::new(),
+        }
+    }
+
+    fn check(&mut self, spec: &str, password: &str) -> Result<Result<(), Failure>, String> {
+        if !self.policies.contains_key(spec) {
+            let policy = self.registry.build(spec)?;
+            self.policies.insert(spec.to_owned(), policy);
+        }
+
+        Ok(self.policies[spec].check(password))
+    }
+}
+
+// -- Policy Line Parser --
+
+// A policy line has the strict format `<min>-<max> <letter>: <password>`, e.g. `1-3 a: abcde`.
+// Separators are exactly one space, the letter is a single (possibly multibyte) character,
+// and the password is any non-empty run of characters without whitespace.
+// Errors point at the column of the offending character, counted in chars from 1.
+
+#[derive(Debug, PartialEq)]
+enum ParseErrorKind {
+    Expected(&'static str),
+    UnexpectedCharacter(char),
+    NumberTooLarge,
+    MinGreaterThanMax { min: usize, max: usize },
+}
+
+#[derive(Debug, PartialEq)]
+struct ParseError {
+    column: usize,
+    kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}: ", self.column)?;
+        match &self.kind {
+            ParseErrorKind::Expected(expected) => write!(f, "expected {}", expected),
+            ParseErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character {:?}", c),
+            ParseErrorKind::NumberTooLarge => write!(f, "number is too large"),
+            ParseErrorKind::MinGreaterThanMax { min, max } => {
+                write!(f, "range start {} is greater than range end {}", min, max)
             }
-            None => {}
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct PolicyLine {
+    range: RangeInclusive<usize>,
+    letter: char,
+    password: String,
+}
+
+struct Cursor {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Cursor {
+    fn new(input: &str) -> Self {
+        Cursor {
+            chars: input.chars().collect(),
+            position: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).cloned()
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            column: self.position + 1,
+            kind,
+        }
+    }
+
+    /// Returns an error for the current character, or for the end of the input when there isn't one.
+    fn unexpected(&self, expected: &'static str) -> ParseError {
+        match self.peek() {
+            Some(c) if c.is_whitespace() => self.error(ParseErrorKind::UnexpectedCharacter(c)),
+            _ => self.error(ParseErrorKind::Expected(expected)),
+        }
+    }
+
+    fn expect(&mut self, c: char, expected: &'static str) -> Result<(), ParseError> {
+        if self.peek() != Some(c) {
+            return Err(self.unexpected(expected));
+        }
+
+        self.position += 1;
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<usize, ParseError> {
+        let start = self.position;
+        let mut value: usize = 0;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(10)) {
+            value = value
+                .checked_mul(10)
+                .and_then(|v| v.checked_add(digit as usize))
+                .ok_or(ParseError {
+                    column: start + 1,
+                    kind: ParseErrorKind::NumberTooLarge,
+                })?;
+            self.position += 1;
+        }
+
+        if self.position == start {
+            return Err(self.unexpected("a number"));
+        }
+
+        Ok(value)
+    }
+
+    /// Parses `<min>-<max> <letter>`
+    fn range_and_letter(&mut self) -> Result<(RangeInclusive<usize>, char), ParseError> {
+        let range_column = self.position + 1;
+        let min = self.number()?;
+        self.expect('-', "'-'")?;
+        let max = self.number()?;
+        if min > max {
+            return Err(ParseError {
+                column: range_column,
+                kind: ParseErrorKind::MinGreaterThanMax { min, max },
+            });
         }
 
-        if has_char_at_first_position && has_char_at_last_position {
-            return false;
-        } else if has_char_at_first_position && !has_char_at_last_position {
-            return true;
-        } else if !has_char_at_first_position && has_char_at_last_position {
-            return true;
+        self.expect(' ', "a space")?;
+        match self.peek() {
+            Some(c) if !c.is_whitespace() => self.position += 1,
+            _ => return Err(self.unexpected("a letter")),
         }
 
-        false
+        Ok((min..=max, self.chars[self.position - 1]))
+    }
+
+    /// Any character left over is unexpected, whether it's whitespace or not.
+    fn end(&self) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(c) => Err(self.error(ParseErrorKind::UnexpectedCharacter(c))),
+            None => Ok(()),
+        }
     }
 }
 
-#[derive(PartialEq)]
-enum PolicyType {
-    Old,
-    New,
+fn parse_policy_line(line: &str) -> Result<PolicyLine, ParseError> {
+    let mut cursor = Cursor::new(line);
+    let (range, letter) = cursor.range_and_letter()?;
+    cursor.expect(':', "':' after a single letter")?;
+    cursor.expect(' ', "a space")?;
+
+    let password = parse_password(&cursor.chars[cursor.position..], cursor.position)?;
+    Ok(PolicyLine {
+        range,
+        letter,
+        password,
+    })
+}
+
+/// A password is a non-empty run of characters without whitespace, that starts at `offset` in the line.
+fn parse_password(chars: &[char], offset: usize) -> Result<String, ParseError> {
+    if chars.is_empty() {
+        return Err(ParseError {
+            column: offset + 1,
+            kind: ParseErrorKind::Expected("a password"),
+        });
+    }
+
+    if let Some(index) = chars.iter().position(|c| c.is_whitespace()) {
+        return Err(ParseError {
+            column: offset + index + 1,
+            kind: ParseErrorKind::UnexpectedCharacter(chars[index]),
+        });
+    }
+
+    Ok(chars.iter().collect())
+}
+
+/// Parses the `1-3 a` parameters shared by the old and new policies.
+fn parse_range_and_letter(params: &str) -> Result<(usize, usize, char), String> {
+    let mut cursor = Cursor::new(params);
+    let (range, letter) = cursor
+        .range_and_letter()
+        .and_then(|result| cursor.end().map(|_| result))
+        .map_err(|e| format!("Invalid parameters {:?} at {}", params, e))?;
+
+    Ok((*range.start(), *range.end(), letter))
+}
+
+fn build_old_policy(params: &str, _: &PolicyRegistry) -> Result<Box<dyn ValidatePassword>, String> {
+    let (min, max, letter) = parse_range_and_letter(params)?;
+    Ok(Box::new(OldPasswordPolicy { min, max, letter }))
 }
 
-fn crop_letters_after(s: &str, pos: usize) -> &str {
-    match s.char_indices().nth(pos) {
-        Some((pos, _)) => &s[..pos],
-        None => "",
+/// Creates a new policy from positions that start at 1, as they do in the input.
+fn new_policy(
+    first_position: usize,
+    last_position: usize,
+    letter: char,
+) -> Result<NewPasswordPolicy, String> {
+    if first_position == 0 || last_position == 0 {
+        return Err("Positions start at 1".to_owned());
     }
+
+    Ok(NewPasswordPolicy {
+        first_position: first_position - 1,
+        last_position: last_position - 1,
+        letter,
+    })
+}
+
+fn build_new_policy(params: &str, _: &PolicyRegistry) -> Result<Box<dyn ValidatePassword>, String> {
+    let (first_position, last_position, letter) = parse_range_and_letter(params)?;
+    Ok(Box::new(new_policy(first_position, last_position, letter)?))
 }
-fn read_input_to_vec(filepath: &str) -> Vec<String> {
-    let mut input: Vec<String> = Vec::new();
-    let contents = fs::read_to_string(filepath).expect(&format!(
-        "Something went wrong file reading the file at {}",
-        filepath
-    ));
-    for line in contents.lines() {
-        input.push(line.to_owned())
-    }
-    return input;
-}
-
-fn is_valid(policy_password: &str, policy_type: &PolicyType) -> bool {
-    let values: Vec<&str> = policy_password.split(" ").collect::<Vec<&str>>();
-    let allowed_password_range = values[0].split("-").collect::<Vec<&str>>();
-    let letter = crop_letters_after(&values[1], 1);
-    let letter = letter
-        .parse::<char>()
-        .expect("policy letter should be a char.");
-
-    let password = &values[2];
-
-    match *policy_type {
-        PolicyType::Old => {
-            let range_min = allowed_password_range[0]
-                .parse::<i32>()
-                .expect("invalid range min");
-
-            let range_max = allowed_password_range[1]
-                .parse::<i32>()
-                .expect("invalid range max");
-
-            let policy = OldPasswordPolicy {
-                min: range_min,
-                max: range_max,
-                letter,
-            };
 
-            return policy.validate(password);
+fn build_regex_policy(
+    params: &str,
+    _: &PolicyRegistry,
+) -> Result<Box<dyn ValidatePassword>, String> {
+    let regex = Regex::new(params).map_err(|e| format!("Invalid regex {:?}: {}", params, e))?;
+    Ok(Box::new(RegexPasswordPolicy { regex }))
+}
+
+fn build_forbidden_characters_policy(
+    params: &str,
+    _: &PolicyRegistry,
+) -> Result<Box<dyn ValidatePassword>, String> {
+    Ok(Box::new(ForbiddenCharactersPolicy {
+        characters: params.chars().filter(|c| !c.is_whitespace()).collect(),
+    }))
+}
+
+fn build_min_distinct_characters_policy(
+    params: &str,
+    _: &PolicyRegistry,
+) -> Result<Box<dyn ValidatePassword>, String> {
+    let min = params
+        .parse::<usize>()
+        .map_err(|_| format!("Invalid number of distinct characters {:?}", params))?;
+    Ok(Box::new(MinDistinctCharactersPolicy { min }))
+}
+
+/// Splits `(a, b(c, d), e)` into the specs `a`, `b(c, d)` and `e`.
+fn split_combinator_params(params: &str) -> Result<Vec<&str>, String> {
+    let inner = params
+        .strip_prefix('(')
+        .and_then(|p| p.strip_suffix(')'))
+        .ok_or(format!("Expected policies in brackets, found {:?}", params))?;
+
+    let mut specs = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err(format!("Unbalanced brackets in {:?}", params)),
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                specs.push(inner[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
         }
-        PolicyType::New => {
-            let first_position = allowed_password_range[0]
-                .parse::<usize>()
-                .expect("invalid first position");
+    }
+
+    if depth != 0 {
+        return Err(format!("Unbalanced brackets in {:?}", params));
+    }
+
+    specs.push(inner[start..].trim());
+    if specs.iter().any(|spec| spec.is_empty()) {
+        return Err(format!("Empty policy in {:?}", params));
+    }
+
+    Ok(specs)
+}
+
+fn build_policies(
+    params: &str,
+    registry: &PolicyRegistry,
+) -> Result<Vec<Box<dyn ValidatePassword>>, String> {
+    split_combinator_params(params)?
+        .into_iter()
+        .map(|spec| registry.build(spec))
+        .collect()
+}
 
-            let last_position = allowed_password_range[1]
-                .parse::<usize>()
-                .expect("invalid last position");
+fn build_and_policy(
+    params: &str,
+    registry: &PolicyRegistry,
+) -> Result<Box<dyn ValidatePassword>, String> {
+    Ok(Box::new(AndPolicy {
+        policies: build_policies(params, registry)?,
+    }))
+}
+
+fn build_or_policy(
+    params: &str,
+    registry: &PolicyRegistry,
+) -> Result<Box<dyn ValidatePassword>, String> {
+    Ok(Box::new(OrPolicy {
+        policies: build_policies(params, registry)?,
+    }))
+}
+
+fn build_not_policy(
+    params: &str,
+    registry: &PolicyRegistry,
+) -> Result<Box<dyn ValidatePassword>, String> {
+    let mut policies = build_policies(params, registry)?;
+    if policies.len() != 1 {
+        return Err(format!("not takes a single policy, found {:?}", params));
+    }
+
+    Ok(Box::new(NotPolicy {
+        policy: policies.remove(0),
+    }))
+}
+
+fn read_input_to_vec(filepath: &str) -> Vec<String> {
+    let contents = fs::read_to_string(filepath)
+        .unwrap_or_else(|_| panic!("Something went wrong file reading the file at {}", filepath));
+
+    contents.lines().map(|line| line.to_owned()).collect()
+}
+
+/// The result of validating a single line of the input.
+#[derive(Debug, PartialEq)]
+struct LineVerdict {
+    /// 1-indexed line number in the input
+    line_number: usize,
+    line: String,
+    result: Result<(), Failure>,
+}
+
+/// The policy that lines with just a range and a letter are checked with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DefaultPolicy {
+    Old,
+    New,
+}
 
-            let policy = NewPasswordPolicy {
-                first_position: first_position - 1,
-                last_position: last_position - 1,
+impl DefaultPolicy {
+    fn check(self, line: &PolicyLine) -> Result<Result<(), Failure>, String> {
+        let (start, end, letter) = (*line.range.start(), *line.range.end(), line.letter);
+        Ok(match self {
+            DefaultPolicy::Old => OldPasswordPolicy {
+                min: start,
+                max: end,
                 letter,
-            };
+            }
+            .check(&line.password),
+            DefaultPolicy::New => new_policy(start, end, letter)?.check(&line.password),
+        })
+    }
+}
+
+/// A line of the input, which is either
+/// * a range, letter and password (`1-3 a: abcde`), checked with the default policies, or
+/// * a policy spec and password (`distinct 4: abcde`), checked with the named policy.
+#[derive(Debug, PartialEq)]
+enum InputLine<'a> {
+    Default(PolicyLine),
+    Named { spec: &'a str, password: String },
+}
 
-            return policy.validate(password);
+fn parse_input_line(policy_password: &str) -> Result<InputLine<'_>, String> {
+    let starts_with_range = policy_password.starts_with(|c: char| c.is_ascii_digit());
+    if starts_with_range {
+        let line = parse_policy_line(policy_password).map_err(|e| e.to_string())?;
+        return Ok(InputLine::Default(line));
+    }
+
+    let (spec, password) = policy_password
+        .rsplit_once(": ")
+        .ok_or(format!("Missing password in {:?}", policy_password))?;
+    let offset = spec.chars().count() + 2;
+    let password: Vec<char> = password.chars().collect();
+    let password = parse_password(&password, offset).map_err(|e| e.to_string())?;
+
+    Ok(InputLine::Named { spec, password })
+}
+
+/// The verdicts of the lines checked with the old and with the new policy,
+/// and of the lines that name their own policy, which are only checked once.
+#[derive(Debug, Default)]
+struct Verdicts {
+    old: Vec<LineVerdict>,
+    new: Vec<LineVerdict>,
+    named: Vec<LineVerdict>,
+}
+
+/// Validates every non-empty line of the input.
+fn validate_lines(
+    policies_and_passwords: &[String],
+    registry: &PolicyRegistry,
+) -> Result<Verdicts, String> {
+    let mut policies = PolicyCache::new(registry);
+    let mut verdicts = Verdicts::default();
+
+    for (index, line) in policies_and_passwords.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let line_error = |e: String| format!("line {}: {}", index + 1, e);
+        let verdict = |result| LineVerdict {
+            line_number: index + 1,
+            line: line.to_owned(),
+            result,
+        };
+
+        match parse_input_line(line).map_err(line_error)? {
+            InputLine::Default(policy_line) => {
+                let old = DefaultPolicy::Old.check(&policy_line).map_err(line_error)?;
+                let new = DefaultPolicy::New.check(&policy_line).map_err(line_error)?;
+                verdicts.old.push(verdict(old));
+                verdicts.new.push(verdict(new));
+            }
+            InputLine::Named { spec, password } => {
+                let result = policies.check(spec, &password).map_err(line_error)?;
+                verdicts.named.push(verdict(result));
+            }
         }
     }
+
+    Ok(verdicts)
 }
 
-fn find_valid_password_count(policies_and_passwords: &Vec<String>, policy_type: PolicyType) -> i32 {
-    let mut count = 0;
+fn find_valid_password_count(verdicts: &[LineVerdict]) -> usize {
+    verdicts.iter().filter(|v| v.result.is_ok()).count()
+}
+
+/// Counts the failing lines by the kind of failure, sorted by kind.
+fn count_failures_by_kind(verdicts: &[LineVerdict]) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for failure in verdicts.iter().filter_map(|v| v.result.as_ref().err()) {
+        *counts.entry(failure.kind()).or_insert(0) += 1;
+    }
 
-    for p in policies_and_passwords {
-        if is_valid(p, &policy_type) {
-            count += 1;
+    counts
+}
+
+/// Lists every failing line with the reason it failed, followed by the number of failures of every kind.
+fn format_report(verdicts: &[LineVerdict]) -> String {
+    let mut report = String::new();
+
+    for verdict in verdicts.iter() {
+        if let Err(failure) = &verdict.result {
+            report += &format!(
+                "line {}: {:?} {}\n",
+                verdict.line_number, verdict.line, failure
+            );
         }
     }
 
-    count
+    let valid = find_valid_password_count(verdicts);
+    report += &format!("{} valid, {} invalid\n", valid, verdicts.len() - valid);
+    for (kind, count) in count_failures_by_kind(verdicts) {
+        report += &format!("  {}: {}\n", kind, count);
+    }
+
+    report
 }
 
 fn main() {
+    // Usage: day-02 <input> [--report]
     let args: Vec<String> = env::args().collect();
     let input_path = match args.get(1) {
         Some(path) => path,
         None => panic!("Input cannot be empty!"),
     };
+    let should_report = args.iter().any(|arg| arg == "--report");
 
     let policies_and_passwords = read_input_to_vec(input_path);
+    let registry = PolicyRegistry::default();
+
+    let verdicts = validate_lines(&policies_and_passwords, &registry)
+        .unwrap_or_else(|e| panic!("Invalid input: {}", e));
+
+    for (name, verdicts) in [("Old", &verdicts.old), ("New", &verdicts.new)].iter() {
+        if should_report {
+            println!("-- {} Policy --", name);
+            print!("{}", format_report(verdicts));
+        }
+
+        let count = find_valid_password_count(verdicts);
+        println!("Number of valid passwords by {} Policy: {}", name, count);
+    }
+
+    // Lines that name their own policy are counted once, apart from the totals of the default policies
+    if !verdicts.named.is_empty() {
+        if should_report {
+            println!("-- Named Policies --");
+            print!("{}", format_report(&verdicts.named));
+        }
+
+        let count = find_valid_password_count(&verdicts.named);
+        println!("Number of valid passwords by their own policy: {}", count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks a single line, with the given policy if it doesn't name its own.
+    fn check_line(
+        policy_password: &str,
+        default_policy: DefaultPolicy,
+        policies: &mut PolicyCache,
+    ) -> Result<Result<(), Failure>, String> {
+        match parse_input_line(policy_password)? {
+            InputLine::Default(line) => default_policy.check(&line),
+            InputLine::Named { spec, password } => policies.check(spec, &password),
+        }
+    }
+
+    fn validate(spec: &str, password: &str) -> bool {
+        PolicyRegistry::default()
+            .build(spec)
+            .unwrap()
+            .validate(password)
+    }
+
+    #[test]
+    fn should_count_valid_passwords_by_default_policy() {
+        let input: Vec<String> = vec![
+            "1-3 a: abcde",
+            "1-3 b: cdefg",
+            "distinct 4: abcde",
+            "2-9 c: ccccccccc",
+            "distinct 4: aaaaa",
+        ]
+        .into_iter()
+        .map(|x| x.to_owned())
+        .collect();
+        let verdicts = validate_lines(&input, &PolicyRegistry::default()).unwrap();
+
+        // The lines that name their own policy are neither counted by the old nor by the new policy.
+        assert_eq!(find_valid_password_count(&verdicts.old), 2);
+        assert_eq!(find_valid_password_count(&verdicts.new), 1);
+        assert_eq!(find_valid_password_count(&verdicts.named), 1);
+        assert_eq!(
+            verdicts
+                .named
+                .iter()
+                .map(|v| v.line_number)
+                .collect::<Vec<usize>>(),
+            vec![3, 5]
+        );
+    }
+
+    #[test]
+    fn should_validate_with_named_policy_per_line() {
+        let registry = PolicyRegistry::default();
+        let mut policies = PolicyCache::new(&registry);
+        assert_eq!(
+            check_line("distinct 5: abcde", DefaultPolicy::Old, &mut policies).map(|r| r.is_ok()),
+            Ok(true)
+        );
+        assert_eq!(
+            check_line("new 1-3 a: abade", DefaultPolicy::Old, &mut policies).map(|r| r.is_ok()),
+            Ok(false)
+        );
+        assert_eq!(
+            check_line("regex ^a.: b: ab", DefaultPolicy::Old, &mut policies).map(|r| r.is_ok()),
+            Ok(false)
+        );
+        assert!(check_line("length 5: abcde", DefaultPolicy::Old, &mut policies).is_err());
+        assert!(check_line("1-3 a abcde", DefaultPolicy::Old, &mut policies).is_err());
+
+        // Every spec that was built is reused by the lines that use it again.
+        assert_eq!(
+            check_line("distinct 5: edcba", DefaultPolicy::Old, &mut policies).map(|r| r.is_ok()),
+            Ok(true)
+        );
+        assert_eq!(policies.policies.len(), 3);
+
+        // Lines with a range are checked with the default policy, without going through the registry.
+        assert_eq!(
+            check_line("1-3 a: abcde", DefaultPolicy::Old, &mut policies).map(|r| r.is_ok()),
+            Ok(true)
+        );
+        assert_eq!(
+            check_line("1-3 a: abcde", DefaultPolicy::New, &mut policies).map(|r| r.is_ok()),
+            Ok(true)
+        );
+        assert_eq!(
+            check_line("0-3 a: abcde", DefaultPolicy::New, &mut policies),
+            Err("Positions start at 1".to_owned())
+        );
+        assert_eq!(policies.policies.len(), 3);
+    }
+
+    #[test]
+    fn should_build_leaf_policies() {
+        assert!(validate("regex ^[a-z]+\\d$", "abc1"));
+        assert!(!validate("regex ^[a-z]+\\d$", "abc"));
 
-    let old_count = find_valid_password_count(&policies_and_passwords, PolicyType::Old);
-    println!("Number of valid passwords by Old Policy: {}", old_count);
+        assert!(validate("forbidden xyz", "abc"));
+        assert!(!validate("forbidden xyz", "abcz"));
 
-    let count = find_valid_password_count(&policies_and_passwords, PolicyType::New);
-    println!("Number of valid passwords by New Policy: {}", count);
+        assert!(validate("distinct 3", "aabbcc"));
+        assert!(!validate("distinct 4", "aabbcc"));
+    }
+
+    #[test]
+    fn should_build_combinators() {
+        let spec = "and(old 1-3 a, forbidden xyz, or(distinct 5, regex ^a))";
+        assert!(validate(spec, "abc"));
+        assert!(!validate(spec, "abcx"));
+        assert!(!validate(spec, "bca"));
+        assert!(validate(spec, "bcdea"));
+
+        assert!(validate("not(regex ^a)", "ba"));
+        assert!(!validate("not(regex ^a)", "ab"));
+
+        assert!(validate("regex ^(a|b){2,3}$", "aba"));
+        assert!(validate("or(regex ^(a|b){2}$, distinct 9)", "ab"));
+    }
+
+    #[test]
+    fn should_not_build_invalid_policies() {
+        let registry = PolicyRegistry::default();
+        assert!(registry.build("old 1-a b").is_err());
+        assert!(registry.build("new 0-3 b").is_err());
+        assert!(registry.build("regex (").is_err());
+        assert!(registry.build("not(distinct 1, distinct 2)").is_err());
+        assert!(registry.build("and(distinct 1").is_err());
+        assert!(registry.build("and(distinct 1,)").is_err());
+        assert!(registry.build("maybe 1-3 a").is_err());
+    }
+
+    #[test]
+    fn should_register_custom_policy() {
+        struct LengthPolicy {
+            min: usize,
+        }
+
+        impl ValidatePassword for LengthPolicy {
+            fn check(&self, password: &str) -> Result<(), Failure> {
+                if password.chars().count() >= self.min {
+                    return Ok(());
+                }
+                Err(Failure::Negated)
+            }
+        }
+
+        let mut registry = PolicyRegistry::default();
+        registry.register("length", |params, _| {
+            let min = params.parse::<usize>().map_err(|e| e.to_string())?;
+            Ok(Box::new(LengthPolicy { min }))
+        });
+        let mut policies = PolicyCache::new(&registry);
+
+        assert_eq!(
+            check_line(
+                "and(length 5, distinct 2): abcde",
+                DefaultPolicy::Old,
+                &mut policies
+            )
+            .map(|r| r.is_ok()),
+            Ok(true)
+        );
+        assert_eq!(
+            check_line("length 6: abcde", DefaultPolicy::Old, &mut policies).map(|r| r.is_ok()),
+            Ok(false)
+        );
+    }
+
+    fn check(spec: &str, password: &str) -> Result<(), Failure> {
+        PolicyRegistry::default()
+            .build(spec)
+            .unwrap()
+            .check(password)
+    }
+
+    #[test]
+    fn should_give_failure_reasons() {
+        assert_eq!(
+            check("old 1-3 b", "cdefg"),
+            Err(Failure::MissingLetter { letter: 'b' })
+        );
+        assert_eq!(
+            check("old 2-3 c", "cdefg"),
+            Err(Failure::BelowMin {
+                letter: 'c',
+                count: 1,
+                min: 2
+            })
+        );
+        assert_eq!(
+            check("old 1-2 c", "ccc"),
+            Err(Failure::AboveMax {
+                letter: 'c',
+                count: 3,
+                max: 2
+            })
+        );
+        assert_eq!(check("old 1-3000000000 a", "aaa"), Ok(()));
+        assert_eq!(
+            check("old 3000000000-4000000000 a", "aaa"),
+            Err(Failure::BelowMin {
+                letter: 'a',
+                count: 3,
+                min: 3_000_000_000
+            })
+        );
+        assert_eq!(
+            check("new 2-9 c", "ccccccccc"),
+            Err(Failure::BothPositions {
+                letter: 'c',
+                first: 2,
+                last: 9
+            })
+        );
+        assert_eq!(
+            check("new 1-3 b", "cdefg"),
+            Err(Failure::NeitherPosition {
+                letter: 'b',
+                first: 1,
+                last: 3
+            })
+        );
+        assert_eq!(
+            check("or(distinct 9, forbidden c)", "abc"),
+            Err(Failure::NoneSatisfied(vec![
+                Failure::TooFewDistinct { count: 3, min: 9 },
+                Failure::ForbiddenCharacter { character: 'c' }
+            ]))
+        );
+        assert_eq!(check("not(distinct 1)", "abc"), Err(Failure::Negated));
+    }
+
+    #[test]
+    fn should_report_failing_lines() {
+        let input: Vec<String> = vec![
+            "1-3 a: abcde",
+            "",
+            "1-3 b: cdefg",
+            "1-2 c: ccccc",
+            "2-9 c: ccccccccc",
+        ]
+        .into_iter()
+        .map(|x| x.to_owned())
+        .collect();
+        let verdicts = validate_lines(&input, &PolicyRegistry::default())
+            .unwrap()
+            .old;
+
+        assert_eq!(verdicts.len(), 4);
+        assert_eq!(verdicts[1].line_number, 3);
+
+        let counts = count_failures_by_kind(&verdicts);
+        assert_eq!(counts.get("missing letter"), Some(&1));
+        assert_eq!(counts.get("above max"), Some(&1));
+
+        let report = format_report(&verdicts);
+        assert!(report.contains("line 3: \"1-3 b: cdefg\" letter 'b' is missing"));
+        assert!(report
+            .contains("line 4: \"1-2 c: ccccc\" letter 'c' occurs 5 times, above the max of 2"));
+        assert!(report.contains("2 valid, 2 invalid"));
+    }
+
+    #[test]
+    fn should_parse_policy_line() {
+        assert_eq!(
+            parse_policy_line("1-3 a: abcde"),
+            Ok(PolicyLine {
+                range: 1..=3,
+                letter: 'a',
+                password: "abcde".to_owned()
+            })
+        );
+
+        // Multibyte letters and passwords
+        assert_eq!(
+            parse_policy_line("2-12 é: çaféé"),
+            Ok(PolicyLine {
+                range: 2..=12,
+                letter: 'é',
+                password: "çaféé".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_malformed_policy_lines() {
+        let cases = vec![
+            ("1-3 a abcde", 6, ParseErrorKind::UnexpectedCharacter(' ')),
+            ("1-3 a:abcde", 7, ParseErrorKind::Expected("a space")),
+            ("1-3  a: abcde", 5, ParseErrorKind::UnexpectedCharacter(' ')),
+            ("1-3 a:  abcde", 8, ParseErrorKind::UnexpectedCharacter(' ')),
+            ("1 -3 a: abcde", 2, ParseErrorKind::UnexpectedCharacter(' ')),
+            (
+                "1-3 a: abc de",
+                11,
+                ParseErrorKind::UnexpectedCharacter(' '),
+            ),
+            (
+                "1-3 a: abcde ",
+                13,
+                ParseErrorKind::UnexpectedCharacter(' '),
+            ),
+            (
+                "1-3 ab: abcde",
+                6,
+                ParseErrorKind::Expected("':' after a single letter"),
+            ),
+            (
+                "1-3 é",
+                6,
+                ParseErrorKind::Expected("':' after a single letter"),
+            ),
+            ("1-3 a: ", 8, ParseErrorKind::Expected("a password")),
+            ("1-3", 4, ParseErrorKind::Expected("a space")),
+            ("1-x a: abcde", 3, ParseErrorKind::Expected("a number")),
+            (
+                "3-1 a: abcde",
+                1,
+                ParseErrorKind::MinGreaterThanMax { min: 3, max: 1 },
+            ),
+            (
+                "99999999999999999999-1 a: b",
+                1,
+                ParseErrorKind::NumberTooLarge,
+            ),
+            ("1-3 é: çaf é", 11, ParseErrorKind::UnexpectedCharacter(' ')),
+        ];
+
+        for (line, column, kind) in cases {
+            assert_eq!(
+                parse_policy_line(line),
+                Err(ParseError { column, kind }),
+                "{:?}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn should_report_positioned_errors_for_lines() {
+        let registry = PolicyRegistry::default();
+        let mut policies = PolicyCache::new(&registry);
+        assert_eq!(
+            check_line("3-1 a: abc", DefaultPolicy::Old, &mut policies),
+            Err("column 1: range start 3 is greater than range end 1".to_owned())
+        );
+        assert_eq!(
+            check_line("distinct 2: a b", DefaultPolicy::Old, &mut policies),
+            Err("column 14: unexpected character ' '".to_owned())
+        );
+        assert!(registry.build("old 3-1 a").is_err());
+        assert_eq!(
+            registry.build("old 1-3 ab").err(),
+            Some("Invalid parameters \"1-3 ab\" at column 6: unexpected character 'b'".to_owned())
+        );
+
+        let input = vec!["1-3 a: abcde".to_owned(), "1-3 a abcde".to_owned()];
+        assert_eq!(
+            validate_lines(&input, &registry).err(),
+            Some("line 2: column 6: unexpected character ' '".to_owned())
+        );
+    }
 }
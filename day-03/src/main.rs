@@ -1,3 +1,6 @@
+use std::collections::{BTreeMap, HashSet};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 use std::{env, fs};
 
 // Day 03
@@ -28,16 +31,55 @@ use std::{env, fs};
 // * While traversing the Grid, if you reach the end of the last column wrap around to the first column using `col[index % col.len()]`.
 // * This is necessary as the problem mentions that the Grid is not fixed on the right, and can extend as much as necessary.
 
+// Slopes can point in any direction, e.g. "left 2, up 1". The toboggan starts at the corner the slope points away from,
+// i.e. on the top row when moving down (bottom row when moving up), and on the left column when moving right (right column when moving left).
+// What happens at the edges of the Grid depends on the EdgeMode. Since every move is the same, the traversal
+// also stops when it gets back to a position it has already visited, as it would go around in circles from there on.
+
 const TREE: char = '#';
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct Jump {
     column: i32,
     row: i32,
 }
 
+/// What happens when the toboggan moves past an edge of the Grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EdgeMode {
+    /// The Grid repeats to the left and right, the traversal stops at the top and bottom.
+    Wrap,
+    /// The Grid repeats in every direction, the traversal stops when it gets back to a visited position.
+    Torus,
+    /// The toboggan stays on the first / last column, the traversal stops at the top and bottom.
+    Clamp,
+    /// The traversal stops at any edge.
+    Stop,
+}
+
+impl EdgeMode {
+    /// Whether the Grid repeats to the left and right.
+    fn wraps_columns(self) -> bool {
+        self == EdgeMode::Wrap || self == EdgeMode::Torus
+    }
+}
+
+impl FromStr for EdgeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(EdgeMode::Wrap),
+            "torus" => Ok(EdgeMode::Torus),
+            "clamp" => Ok(EdgeMode::Clamp),
+            "stop" => Ok(EdgeMode::Stop),
+            _ => Err(format!("Invalid edge mode {}", s)),
+        }
+    }
+}
+
 // Creates a 2D Vector of Characters
-fn create_grid<'a>(input: &str) -> Vec<Vec<char>> {
+fn create_grid(input: &str) -> Vec<Vec<char>> {
     let mut grid = Vec::new();
     for line in input.lines() {
         let elements_in_row: Vec<char> = line.chars().collect();
@@ -51,12 +93,16 @@ fn create_grid<'a>(input: &str) -> Vec<Vec<char>> {
 fn parse_slope(slope: &str) -> Jump {
     let mut jump = Jump { column: 0, row: 0 };
 
-    let navigation_instructions: Vec<&str> = slope.split(",").collect();
+    let navigation_instructions: Vec<&str> = slope.split(',').collect();
     for instruction in navigation_instructions.iter() {
         let slope_vec: Vec<&str> = instruction.split_whitespace().collect();
-        let direction = slope_vec.get(0).expect(&format!("Invalid slope {}", slope));
+        let direction = slope_vec
+            .first()
+            .unwrap_or_else(|| panic!("Invalid slope {}", slope));
 
-        let step = slope_vec.get(1).expect(&format!("Invalid step {}", slope));
+        let step = slope_vec
+            .get(1)
+            .unwrap_or_else(|| panic!("Invalid step {}", slope));
 
         let step = step.parse::<i32>().unwrap();
 
@@ -72,58 +118,405 @@ fn parse_slope(slope: &str) -> Jump {
     jump
 }
 
-/// Returns the number of characters found while traversing along a given slope.
-fn get_character_count_along_slope(grid: &mut Vec<Vec<char>>, slope: &str, character_to_count: char) -> i32 {
-    let mut count = 0;
-    let jump = parse_slope(slope);
+/// Moves a single coordinate by `step`, within `0..len`. Returns None when the traversal should stop.
+fn move_along(
+    position: usize,
+    step: i32,
+    len: usize,
+    edge_mode: EdgeMode,
+    wraps: bool,
+) -> Option<usize> {
+    let next = position as i64 + step as i64;
+    let len = len as i64;
+
+    if (0..len).contains(&next) {
+        return Some(next as usize);
+    }
 
-    let col_len = grid[0].len();
+    match edge_mode {
+        _ if wraps => Some(next.rem_euclid(len) as usize),
+        EdgeMode::Clamp => Some(next.clamp(0, len - 1) as usize),
+        _ => None,
+    }
+}
+
+/// Returns the corner the slope points away from.
+fn start_position(row_len: usize, col_len: usize, jump: &Jump) -> (usize, usize) {
+    let row = if jump.row < 0 { row_len - 1 } else { 0 };
+    let col = if jump.column < 0 { col_len - 1 } else { 0 };
+    (row, col)
+}
 
-    let mut col_index = 0;
-    let mut row_index = 0;
+/// Returns the positions (row, column) visited while traversing along a given slope, excluding the start.
+fn traverse(grid: &[Vec<char>], jump: &Jump, edge_mode: EdgeMode) -> Vec<(usize, usize)> {
+    let mut positions = Vec::new();
+    let (row_len, col_len) = match grid.first() {
+        Some(row) if !row.is_empty() => (grid.len(), row.len()),
+        _ => return positions,
+    };
 
-    for row in grid.iter() {
-        col_index = col_index + jump.column as usize;
+    let (mut row_index, mut col_index) = start_position(row_len, col_len, jump);
 
-        if row_index >= row.len() {
-            row_index = row_index + jump.row as usize % row.len();
+    let mut visited = HashSet::new();
+    visited.insert((row_index, col_index));
+
+    loop {
+        // rows only repeat for Torus. Clamping only applies to columns.
+        let row_mode = if edge_mode == EdgeMode::Clamp {
+            EdgeMode::Stop
         } else {
-            row_index = row_index + jump.row as usize;
+            edge_mode
+        };
+
+        let next_row = move_along(
+            row_index,
+            jump.row,
+            row_len,
+            row_mode,
+            edge_mode == EdgeMode::Torus,
+        );
+        let next_col = move_along(
+            col_index,
+            jump.column,
+            col_len,
+            edge_mode,
+            edge_mode.wraps_columns(),
+        );
+
+        match (next_row, next_col) {
+            (Some(row), Some(col)) if visited.insert((row, col)) => {
+                row_index = row;
+                col_index = col;
+                positions.push((row, col));
+            }
+            _ => break,
         }
+    }
 
-        if let Some(new_row) = grid.get(row_index) {
-            if let Some(value) = new_row.get(col_index % col_len) {
-                if *value == character_to_count {
-                    count += 1
-                }
+    positions
+}
+
+/// Everything met while traversing along a slope: the positions (row, column) in the order they were visited,
+/// and how many times every character was met. Neither includes the start.
+/// When the Grid repeats, `tiled_path` has the rows and columns counted across the copies of the Grid,
+/// i.e. column -1 is the last column of the copy to the left, and row -1 the last row of the copy above.
+#[derive(Debug, PartialEq)]
+struct Traversal {
+    path: Vec<(usize, usize)>,
+    tiled_path: Vec<(i64, i64)>,
+    histogram: BTreeMap<char, usize>,
+}
+
+impl Traversal {
+    fn new(grid: &[Vec<char>], jump: &Jump, edge_mode: EdgeMode) -> Self {
+        let path = traverse(grid, jump, edge_mode);
+
+        let mut histogram = BTreeMap::new();
+        for (row, col) in path.iter() {
+            if let Some(c) = grid[*row].get(*col) {
+                *histogram.entry(*c).or_insert(0) += 1;
+            }
+        }
+
+        // Every move is the same, so the n-th position is n jumps away from the start.
+        let (start_row, start_col) = match grid.first() {
+            Some(row) => start_position(grid.len(), row.len(), jump),
+            None => (0, 0),
+        };
+        let tiled_path = path
+            .iter()
+            .zip(1..)
+            .map(|((row, col), n)| {
+                let row = match edge_mode {
+                    EdgeMode::Torus => start_row as i64 + n * jump.row as i64,
+                    _ => *row as i64,
+                };
+                let col = match edge_mode.wraps_columns() {
+                    true => start_col as i64 + n * jump.column as i64,
+                    false => *col as i64,
+                };
+                (row, col)
+            })
+            .collect();
+
+        Traversal {
+            path,
+            tiled_path,
+            histogram,
+        }
+    }
+
+    fn count(&self, character: char) -> usize {
+        self.histogram.get(&character).copied().unwrap_or(0)
+    }
+}
+
+/// Returns the number of characters found while traversing along a given slope.
+fn get_character_count_along_slope(
+    grid: &[Vec<char>],
+    slope: &str,
+    character_to_count: char,
+    edge_mode: EdgeMode,
+) -> i32 {
+    let jump = parse_slope(slope);
+    Traversal::new(grid, &jump, edge_mode).count(character_to_count) as i32
+}
+
+fn process(input: &str, direction: &str, edge_mode: EdgeMode) -> i32 {
+    let grid = create_grid(input);
+    get_character_count_along_slope(&grid, direction, TREE, edge_mode)
+}
+
+const HIT: char = 'X';
+const MISS: char = 'O';
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RenderStyle {
+    Plain,
+    /// Hits in red and misses in green, using ANSI escape codes.
+    Ansi,
+}
+
+/// Draws the path of a traversal onto the Grid, as in the puzzle text: trees that were hit are marked with an X,
+/// and open squares that were passed with an O. The Grid is repeated in every direction the path wraps around in,
+/// for as far as the path reaches.
+fn render_path(grid: &[Vec<char>], traversal: &Traversal, style: RenderStyle) -> String {
+    let width = grid.first().map_or(0, |row| row.len()) as i64;
+    let height = grid.len() as i64;
+    if width == 0 {
+        return String::new();
+    }
+
+    // The range of copies of the Grid the path passes through, always including the Grid itself.
+    let tile_range = |tiles: Vec<i64>| {
+        let first = tiles.iter().copied().min().unwrap_or(0).min(0);
+        let last = tiles.iter().copied().max().unwrap_or(0).max(0);
+        (first, last)
+    };
+    let (first_row_tile, last_row_tile) = tile_range(
+        traversal
+            .tiled_path
+            .iter()
+            .map(|(row, _)| row.div_euclid(height))
+            .collect(),
+    );
+    let (first_tile, last_tile) = tile_range(
+        traversal
+            .tiled_path
+            .iter()
+            .map(|(_, col)| col.div_euclid(width))
+            .collect(),
+    );
+    let marked: HashSet<&(i64, i64)> = traversal.tiled_path.iter().collect();
+
+    let mut rendered = String::new();
+    for row_index in first_row_tile * height..(last_row_tile + 1) * height {
+        let row = &grid[row_index.rem_euclid(height) as usize];
+        for col in first_tile * width..(last_tile + 1) * width {
+            let c = row
+                .get(col.rem_euclid(width) as usize)
+                .copied()
+                .unwrap_or(' ');
+            if !marked.contains(&(row_index, col)) {
+                rendered.push(c);
+                continue;
+            }
+
+            let (mark, colour) = if c == TREE { (HIT, 31) } else { (MISS, 32) };
+            match style {
+                RenderStyle::Plain => rendered.push(mark),
+                RenderStyle::Ansi => rendered.push_str(&format!("\x1b[{}m{}\x1b[0m", colour, mark)),
             }
         }
+        rendered.push('\n');
     }
 
-    count
+    rendered
+}
+
+/// Where the trees are in every row of the Grid, worked out once and shared by all the slopes in a search.
+struct TreeRows {
+    width: usize,
+    rows: Vec<Vec<bool>>,
 }
 
-fn process(input: &str, direction: &str) -> i32 {
-    let mut grid = create_grid(&input);
-    let count = get_character_count_along_slope(&mut grid, direction, TREE);
-    count
+impl TreeRows {
+    fn new(grid: &[Vec<char>]) -> Self {
+        let width = grid.first().map_or(0, |row| row.len());
+        let rows = grid
+            .iter()
+            .map(|row| (0..width).map(|col| row.get(col) == Some(&TREE)).collect())
+            .collect();
+
+        TreeRows { width, rows }
+    }
+
+    /// Counts the trees met on the given rows, moving `right` columns per row with the Grid repeating to the left and right.
+    /// Like `traverse`, a slope moving left starts on the last column, and the starting position isn't counted.
+    fn count(&self, rows: &[&Vec<bool>], right: i32) -> usize {
+        let width = self.width as i64;
+        let mut col = if right < 0 { width - 1 } else { 0 };
+
+        rows.iter()
+            .filter(|row| {
+                col = (col + right as i64).rem_euclid(width);
+                row[col as usize]
+            })
+            .count()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct SlopeCount {
+    jump: Jump,
+    trees: usize,
+}
+
+/// Counts the trees for every slope (right r, down d) within the given bounds, with the Grid repeating to the left and right.
+/// The result is ranked by the number of trees, from fewest to most.
+fn search_slopes(
+    grid: &[Vec<char>],
+    rights: RangeInclusive<i32>,
+    downs: RangeInclusive<i32>,
+) -> Result<Vec<SlopeCount>, String> {
+    if *downs.start() < 1 {
+        return Err(format!(
+            "Slopes have to move down at least 1 row, got {}",
+            downs.start()
+        ));
+    }
+
+    let tree_rows = TreeRows::new(grid);
+    let mut ranked = Vec::new();
+    if tree_rows.width == 0 {
+        return Ok(ranked);
+    }
+
+    for down in downs {
+        // Every slope moving down by the same number of rows visits the same rows.
+        let rows: Vec<&Vec<bool>> = tree_rows
+            .rows
+            .iter()
+            .step_by(down as usize)
+            .skip(1)
+            .collect();
+
+        for right in rights.clone() {
+            ranked.push(SlopeCount {
+                jump: Jump {
+                    column: right,
+                    row: down,
+                },
+                trees: tree_rows.count(&rows, right),
+            });
+        }
+    }
+
+    ranked.sort_by_key(|slope| (slope.trees, slope.jump.row, slope.jump.column));
+    Ok(ranked)
+}
+
+/// Returns the slopes with the fewest and the most trees from a ranked search, including ties.
+fn fewest_and_most(ranked: &[SlopeCount]) -> (&[SlopeCount], &[SlopeCount]) {
+    let (first, last) = match (ranked.first(), ranked.last()) {
+        (Some(first), Some(last)) => (first.trees, last.trees),
+        _ => return (&[], &[]),
+    };
+
+    let fewest = ranked.partition_point(|slope| slope.trees == first);
+    let most = ranked.partition_point(|slope| slope.trees < last);
+    (&ranked[..fewest], &ranked[most..])
+}
+
+/// Parses inclusive bounds written as `min:max`, e.g. `-3:5`
+fn parse_bounds(bounds: &str) -> Result<RangeInclusive<i32>, String> {
+    let invalid = || format!("Invalid bounds {}, expected min:max", bounds);
+    let (min, max) = bounds.split_once(':').ok_or_else(invalid)?;
+    let min = min.trim().parse::<i32>().map_err(|_| invalid())?;
+    let max = max.trim().parse::<i32>().map_err(|_| invalid())?;
+
+    Ok(min..=max)
+}
+
+fn print_slope_search(input: &str, rights: &str, downs: &str) -> Result<(), String> {
+    let grid = create_grid(input);
+    let ranked = search_slopes(&grid, parse_bounds(rights)?, parse_bounds(downs)?)?;
+    let (fewest, most) = fewest_and_most(&ranked);
+
+    println!("-- Slope search --");
+    println!("Evaluated {} slopes", ranked.len());
+    for (label, slopes) in [("Fewest", fewest), ("Most", most)].iter() {
+        for slope in slopes.iter() {
+            println!(
+                "{} trees: {} for right {}, down {}",
+                label, slope.trees, slope.jump.column, slope.jump.row
+            );
+        }
+    }
+
+    Ok(())
 }
 
 fn main() {
+    // Usage: day-03 <input> [wrap|torus|clamp|stop]
+    //        day-03 <input> --search <right min:max> <down min:max>
+    //        day-03 <input> --render <slope> [plain|ansi] [wrap|torus|clamp|stop]
     let args: Vec<String> = env::args().collect();
     let filepath = args.get(1).expect("Input file cannot be empty!");
     let input = fs::read_to_string(filepath).expect("Something went wrong while reading input");
 
+    if args.get(2).map(String::as_str) == Some("--search") {
+        let rights = args.get(3).expect("Bounds for right cannot be empty!");
+        let downs = args.get(4).expect("Bounds for down cannot be empty!");
+        print_slope_search(&input, rights, downs).unwrap_or_else(|e| panic!("{}", e));
+        return;
+    }
+
+    if args.get(2).map(String::as_str) == Some("--render") {
+        let jump = parse_slope(args.get(3).expect("Slope cannot be empty!"));
+        let style = match args.get(4).map(String::as_str) {
+            None | Some("plain") => RenderStyle::Plain,
+            Some("ansi") => RenderStyle::Ansi,
+            Some(style) => panic!("Invalid render style {}", style),
+        };
+        let edge_mode = match args.get(5) {
+            Some(mode) => mode.parse::<EdgeMode>().unwrap_or_else(|e| panic!("{}", e)),
+            None => EdgeMode::Wrap,
+        };
+
+        let grid = create_grid(&input);
+        print!(
+            "{}",
+            render_path(&grid, &Traversal::new(&grid, &jump, edge_mode), style)
+        );
+        return;
+    }
+
+    let edge_mode = match args.get(2) {
+        Some(mode) => mode.parse::<EdgeMode>().unwrap_or_else(|e| panic!("{}", e)),
+        None => EdgeMode::Wrap,
+    };
+
     // -- Part one --
     println!("-- Part one --");
-    let tree_count_r3_d1 = process(&input, "right 3, down 1");
+    let tree_count_r3_d1 = process(&input, "right 3, down 1", edge_mode);
     println!(
         "Number of trees for Slope - right 3; down 1: {}",
         tree_count_r3_d1
     );
 
+    let traversal = Traversal::new(
+        &create_grid(&input),
+        &parse_slope("right 3, down 1"),
+        edge_mode,
+    );
+    println!("Positions visited: {}", traversal.path.len());
+    for (c, count) in traversal.histogram.iter() {
+        println!("{:?}: {}", c, count);
+    }
+
     // -- Part Two --
-    let slopes = vec![
+    let slopes = [
         "right 1, down 1",
         "right 3, down 1",
         "right 5, down 1",
@@ -131,12 +524,12 @@ fn main() {
         "right 1, down 2",
     ];
 
-    println!("");
+    println!();
     println!("-- Part two --");
 
     // Product of number of trees across slopes
     let product: i64 = slopes.iter().fold(1, |acc: i64, direction| {
-        let count = process(&input, direction);
+        let count = process(&input, direction, edge_mode);
         println!("{}: {}", direction, count);
         acc * count as i64
     });
@@ -158,7 +551,7 @@ mod tests {
 #...
 .#.."###;
 
-        let actual = create_grid(&input);
+        let actual = create_grid(input);
         let expected = vec![
             vec!['.', '.', '#', '#'],
             vec!['#', '.', '.', '.'],
@@ -191,9 +584,241 @@ mod tests {
 #.##...#...
 #...##....#
 .#..#...#.#",
-                "right 3, down 1"
+                "right 3, down 1",
+                EdgeMode::Wrap
             ),
             7
         )
     }
+
+    const MAP: &str = "..##.......
+#...#...#..
+.#....#..#.
+..#.#...#.#
+.#...##..#.
+..#.##.....
+.#.#.#....#
+.#........#
+#.##...#...
+#...##....#
+.#..#...#.#";
+
+    #[test]
+    fn should_count_trees_for_puzzle_slopes() {
+        let counts: Vec<i32> = [
+            "right 1, down 1",
+            "right 3, down 1",
+            "right 5, down 1",
+            "right 7, down 1",
+            "right 1, down 2",
+        ]
+        .iter()
+        .map(|slope| process(MAP, slope, EdgeMode::Wrap))
+        .collect();
+        assert_eq!(counts, vec![2, 7, 3, 4, 2]);
+    }
+
+    #[test]
+    fn should_traverse_upwards_and_leftwards() {
+        let grid = create_grid(MAP);
+
+        // Going up from the bottom-left corner mirrors going down on the flipped map.
+        let mut flipped = grid.clone();
+        flipped.reverse();
+        let up: Vec<(usize, usize)> =
+            traverse(&grid, &parse_slope("right 3, up 1"), EdgeMode::Wrap);
+        let down = traverse(&flipped, &parse_slope("right 3, down 1"), EdgeMode::Wrap);
+        assert_eq!(up.len(), 10);
+        assert_eq!(
+            up,
+            down.iter()
+                .map(|(r, c)| (10 - r, *c))
+                .collect::<Vec<(usize, usize)>>()
+        );
+
+        // Going left from the top-right corner wraps around to the right edge.
+        let left = traverse(&grid, &parse_slope("left 3, down 1"), EdgeMode::Wrap);
+        assert_eq!(left[0], (1, 7));
+        assert_eq!(left[3], (4, 9));
+        assert_eq!(left.len(), 10);
+    }
+
+    #[test]
+    fn should_traverse_with_edge_modes() {
+        let grid = create_grid(MAP);
+        let jump = parse_slope("right 3, down 1");
+
+        let stop = traverse(&grid, &jump, EdgeMode::Stop);
+        assert_eq!(stop, vec![(1, 3), (2, 6), (3, 9)]);
+
+        let clamp = traverse(&grid, &jump, EdgeMode::Clamp);
+        assert_eq!(clamp.len(), 10);
+        assert_eq!(clamp[3], (4, 10));
+        assert_eq!(clamp[9], (10, 10));
+
+        // 11 rows by 11 columns: it takes 11 moves to get back to the start.
+        let torus = traverse(&grid, &jump, EdgeMode::Torus);
+        assert_eq!(torus.len(), 10);
+        assert_eq!(torus[9], (10, 8));
+        let torus = traverse(&grid, &parse_slope("right 1, up 2"), EdgeMode::Torus);
+        assert_eq!(torus.len(), 10);
+    }
+
+    #[test]
+    fn should_terminate_for_any_slope() {
+        let grid = create_grid(MAP);
+        for mode in [
+            EdgeMode::Wrap,
+            EdgeMode::Torus,
+            EdgeMode::Clamp,
+            EdgeMode::Stop,
+        ]
+        .iter()
+        {
+            for row in -3..=3 {
+                for column in -3..=3 {
+                    let positions = traverse(&grid, &Jump { column, row }, *mode);
+                    assert!(positions.len() <= 11 * 11);
+                }
+            }
+        }
+
+        assert!(traverse(&grid, &Jump { column: 0, row: 0 }, EdgeMode::Torus).is_empty());
+        assert_eq!(
+            traverse(&grid, &Jump { column: 1, row: 0 }, EdgeMode::Wrap).len(),
+            10
+        );
+        assert!(traverse(&[], &Jump { column: 1, row: 1 }, EdgeMode::Wrap).is_empty());
+    }
+
+    #[test]
+    fn should_search_slopes() {
+        let grid = create_grid(MAP);
+        let ranked = search_slopes(&grid, -4..=7, 1..=3).unwrap();
+        assert_eq!(ranked.len(), 12 * 3);
+
+        // Every count agrees with walking the slope.
+        for slope in ranked.iter() {
+            let positions = traverse(&grid, &slope.jump, EdgeMode::Wrap);
+            let trees = positions
+                .iter()
+                .filter(|(r, c)| grid[*r][*c] == TREE)
+                .count();
+            assert_eq!(slope.trees, trees, "{:?}", slope.jump);
+        }
+
+        let trees_for = |column, row| {
+            ranked
+                .iter()
+                .find(|slope| slope.jump == Jump { column, row })
+                .unwrap()
+                .trees
+        };
+        assert_eq!(trees_for(3, 1), 7);
+        assert_eq!(trees_for(1, 2), 2);
+
+        let (fewest, most) = fewest_and_most(&ranked);
+        assert!(fewest.iter().all(|slope| slope.trees == ranked[0].trees));
+        assert!(most
+            .iter()
+            .all(|slope| slope.trees == ranked[ranked.len() - 1].trees));
+        assert!(ranked.windows(2).all(|pair| pair[0].trees <= pair[1].trees));
+    }
+
+    #[test]
+    fn should_not_search_invalid_bounds() {
+        let grid = create_grid(MAP);
+        assert!(search_slopes(&grid, 1..=3, 0..=2).is_err());
+        assert!(search_slopes(&[], 1..=3, 1..=2).unwrap().is_empty());
+        assert_eq!(parse_bounds("-3:5"), Ok(-3..=5));
+        assert!(parse_bounds("3-5").is_err());
+    }
+
+    #[test]
+    fn should_collect_histogram_and_path() {
+        let grid = create_grid(MAP);
+        let traversal = Traversal::new(&grid, &parse_slope("right 3, down 1"), EdgeMode::Wrap);
+
+        assert_eq!(traversal.path.len(), 10);
+        assert_eq!(&traversal.path[..4], &[(1, 3), (2, 6), (3, 9), (4, 1)]);
+        assert_eq!(traversal.count(TREE), 7);
+        assert_eq!(traversal.count('.'), 3);
+        assert_eq!(traversal.count('X'), 0);
+        assert_eq!(
+            traversal.histogram.values().sum::<usize>(),
+            traversal.path.len()
+        );
+
+        let grid = create_grid("abc\nbca\ncab\n");
+        let traversal = Traversal::new(&grid, &parse_slope("right 1, down 1"), EdgeMode::Wrap);
+        assert_eq!(traversal.path, vec![(1, 1), (2, 2)]);
+        assert_eq!(
+            traversal
+                .histogram
+                .into_iter()
+                .collect::<Vec<(char, usize)>>(),
+            vec![('b', 1), ('c', 1)]
+        );
+    }
+
+    #[test]
+    fn should_render_path() {
+        let grid = create_grid(MAP);
+        let traversal = Traversal::new(&grid, &parse_slope("right 3, down 1"), EdgeMode::Wrap);
+        let rendered = render_path(&grid, &traversal, RenderStyle::Plain);
+
+        let expected = "\
+..##.........##.........##.......
+#..O#...#..#...#...#..#...#...#..
+.#....X..#..#....#..#..#....#..#.
+..#.#...#O#..#.#...#.#..#.#...#.#
+.#...##..#..X...##..#..#...##..#.
+..#.##.......#.X#.......#.##.....
+.#.#.#....#.#.#.#.O..#.#.#.#....#
+.#........#.#........X.#........#
+#.##...#...#.##...#...#.X#...#...
+#...##....##...##....##...#X....#
+.#..#...#.#.#..#...#.#.#..#...X.#
+";
+        assert_eq!(rendered, expected);
+
+        let ansi = render_path(&grid, &traversal, RenderStyle::Ansi);
+        assert_eq!(ansi.matches("\x1b[31mX\x1b[0m").count(), 7);
+        assert_eq!(ansi.matches("\x1b[32mO\x1b[0m").count(), 3);
+    }
+
+    #[test]
+    fn should_render_path_to_the_left() {
+        let grid = create_grid("#.\n.#\n#.\n");
+        let traversal = Traversal::new(&grid, &parse_slope("left 1, down 1"), EdgeMode::Wrap);
+        assert_eq!(traversal.tiled_path, vec![(1, 0), (2, -1)]);
+        assert_eq!(
+            render_path(&grid, &traversal, RenderStyle::Plain),
+            "#.#.\n.#O#\n#O#.\n"
+        );
+
+        // Without repeating columns the path stays on the Grid.
+        let traversal = Traversal::new(&grid, &parse_slope("left 1, down 1"), EdgeMode::Clamp);
+        assert_eq!(
+            render_path(&grid, &traversal, RenderStyle::Plain),
+            "#.\nO#\nX.\n"
+        );
+    }
+
+    #[test]
+    fn should_render_path_wrapping_vertically() {
+        let grid = create_grid("#.\n.#\n#.\n");
+        let traversal = Traversal::new(&grid, &parse_slope("up 1, right 1"), EdgeMode::Torus);
+        assert_eq!(traversal.path, vec![(1, 1), (0, 0), (2, 1), (1, 0), (0, 1)]);
+        assert_eq!(
+            traversal.tiled_path,
+            vec![(1, 1), (0, 2), (-1, 3), (-2, 4), (-3, 5)]
+        );
+
+        // The rows are repeated above the Grid, so the path doesn't fold back onto the Grid itself.
+        assert_eq!(
+            render_path(&grid, &traversal, RenderStyle::Plain),
+            "#.#.#O\n.#.#O#\n#.#O#.\n#.X.#.\n.X.#.#\n#.#.#.\n"
+        );
+    }
 }
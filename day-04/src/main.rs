@@ -1,150 +1,967 @@
 #[macro_use]
 extern crate lazy_static;
 use regex::Regex;
-use std::collections::HashMap;
-use std::{env, fs};
-
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::iter::Peekable;
+use std::ops::RangeInclusive;
+use std::str::{Chars, FromStr};
+use std::{env, fmt, fs};
 
 // Day 04
 // Given a batch of lines, indicated as passports validated if the passports have the necessary fields, and if the field-values are valid.
 
-fn crop_letters(s: &str, pos: usize) -> &str {
-    match s.char_indices().nth(pos) {
-        Some((pos, _)) => &s[pos..],
-        None => "",
-    }
+// The fields, and what makes them valid, are described by a Schema. Schemas are read from a text file, with one rule per line:
+// <field> <required|optional> <type> [constraint]
+//
+// Types:
+// * any                          - any value
+// * int <min>..<max> [digits=N]  - a whole number within the range (inclusive), written with exactly N digits
+// * regex <pattern>              - a value matching the pattern from start to end
+// * enum <value> <value> ...     - one of the listed values
+// * unit <min>..<max><unit> ...  - a whole number directly followed by a unit, each unit with its own range (inclusive)
+//
+// Empty lines and lines starting with # are ignored. The default Schema holds the rules from the puzzle.
+//
+// Whole numbers are only ever made of ASCII digits, with an optional leading - (no +, spaces or decimals),
+// so a value either matches the grammar of its field exactly or is invalid. Checking a value never panics.
+
+const DEFAULT_RULES: &str = r#"
+byr required int 1920..2002 digits=4
+iyr required int 2010..2020 digits=4
+eyr required int 2020..2030 digits=4
+hgt required unit 150..193cm 59..76in
+hcl required regex #[0-9a-f]{6}
+ecl required enum amb blu brn gry grn hzl oth
+pid required regex [0-9]{9}
+cid optional any
+"#;
+
+lazy_static! {
+    static ref DEFAULT_SCHEMA: Schema =
+        Schema::parse(DEFAULT_RULES).expect("Default rules should be valid");
 }
 
-fn validate_field(field: &str, value: &str) -> bool {
-    lazy_static! {
-        static ref HEIGHT_REGEX: Regex = Regex::new(r"(\d+)(\w+)").unwrap();
+#[derive(Debug, PartialEq)]
+struct UnitRange {
+    unit: String,
+    min: i64,
+    max: i64,
+}
+
+#[derive(Debug)]
+enum FieldType {
+    Any,
+    IntRange {
+        min: i64,
+        max: i64,
+        digits: Option<usize>,
+    },
+    Pattern {
+        pattern: String,
+        regex: Regex,
+    },
+    OneOf(Vec<String>),
+    Measurement(Vec<UnitRange>),
+}
+
+#[derive(Debug)]
+struct FieldRule {
+    field: String,
+    required: bool,
+    field_type: FieldType,
+}
+
+#[derive(Debug)]
+struct Schema {
+    rules: Vec<FieldRule>,
+}
+
+/// Parses an inclusive range written as `min..max`, returning the range and whatever follows the max.
+fn parse_range(s: &str) -> Result<(i64, i64, &str), String> {
+    let invalid = || format!("Invalid range {}, expected min..max", s);
+    let (min, rest) = s.split_once("..").ok_or_else(invalid)?;
+    let digits_end = rest
+        .char_indices()
+        .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && *c == '-')))
+        .map_or(rest.len(), |(i, _)| i);
+
+    let min = min.parse::<i64>().map_err(|_| invalid())?;
+    let max = rest[..digits_end].parse::<i64>().map_err(|_| invalid())?;
+    if min > max {
+        return Err(format!("Invalid range {}, min is greater than max", s));
     }
 
-    let eye_colors = vec!["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
-    match field {
-        "byr" => {
-            value.len() == 4 && {
-                let year = value.parse::<i32>().unwrap();
-                return year >= 1920 && year <= 2002;
+    Ok((min, max, &rest[digits_end..]))
+}
+
+/// Whether the value is one or more ASCII digits.
+fn is_digits(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit())
+}
+
+impl FieldType {
+    fn parse(type_name: &str, constraint: &[&str]) -> Result<Self, String> {
+        match (type_name, constraint) {
+            ("any", []) => Ok(FieldType::Any),
+            ("int", [range]) | ("int", [range, _]) => {
+                let (min, max, rest) = parse_range(range)?;
+                if !rest.is_empty() {
+                    return Err(format!("Invalid range {}", range));
+                }
+
+                let digits = match constraint.get(1) {
+                    Some(digits) => Some(
+                        digits
+                            .strip_prefix("digits=")
+                            .and_then(|n| n.parse::<usize>().ok())
+                            .ok_or(format!("Invalid digit count {}", digits))?,
+                    ),
+                    None => None,
+                };
+
+                Ok(FieldType::IntRange { min, max, digits })
             }
+            ("regex", [pattern]) => Regex::new(&format!("^(?:{})$", pattern))
+                .map(|regex| FieldType::Pattern {
+                    pattern: pattern.to_string(),
+                    regex,
+                })
+                .map_err(|e| format!("Invalid regex {}: {}", pattern, e)),
+            ("enum", values) if !values.is_empty() => Ok(FieldType::OneOf(
+                values.iter().map(|v| v.to_string()).collect(),
+            )),
+            ("unit", ranges) if !ranges.is_empty() => ranges
+                .iter()
+                .map(|range| {
+                    let (min, max, unit) = parse_range(range)?;
+                    if unit.is_empty() {
+                        return Err(format!("Missing unit in {}", range));
+                    }
+
+                    Ok(UnitRange {
+                        unit: unit.to_string(),
+                        min,
+                        max,
+                    })
+                })
+                .collect::<Result<Vec<UnitRange>, String>>()
+                .map(FieldType::Measurement),
+            ("any", _) | ("int", _) | ("regex", _) | ("enum", _) | ("unit", _) => Err(format!(
+                "Invalid constraint for {}: {}",
+                type_name,
+                constraint.join(" ")
+            )),
+            _ => Err(format!("Unknown field type {}", type_name)),
         }
-        "iyr" => {
-            value.len() == 4 && {
-                let year = value.parse::<i32>().unwrap();
-                return year >= 2010 && year <= 2020;
+    }
+
+    /// Checks a value against the type, returning the rule that was broken, e.g. `exceeds 76in`
+    fn check(&self, value: &str) -> Result<(), String> {
+        let check_range = |number: i64, min: i64, max: i64, unit: &str| {
+            if number < min {
+                Err(format!("is below {}{}", min, unit))
+            } else if number > max {
+                Err(format!("exceeds {}{}", max, unit))
+            } else {
+                Ok(())
+            }
+        };
+
+        match self {
+            FieldType::Any => Ok(()),
+            FieldType::IntRange { min, max, digits } => {
+                let unsigned = value.strip_prefix('-').unwrap_or(value);
+                if !is_digits(unsigned) {
+                    return Err("is not a whole number".to_string());
+                }
+
+                if let Some(digits) = digits.filter(|digits| unsigned.len() != *digits) {
+                    return Err(format!("does not have {} digits", digits));
+                }
+
+                let number = value
+                    .parse::<i64>()
+                    .map_err(|_| "is too large".to_string())?;
+                check_range(number, *min, *max, "")
+            }
+            FieldType::Pattern { regex, .. } if regex.is_match(value) => Ok(()),
+            FieldType::Pattern { pattern, .. } => Err(format!("does not match {}", pattern)),
+            FieldType::OneOf(values) if values.iter().any(|v| v == value) => Ok(()),
+            FieldType::OneOf(values) => Err(format!("is not one of {}", values.join(", "))),
+            FieldType::Measurement(units) => {
+                let digits_end = value
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(value.len());
+                let (number, unit) = value.split_at(digits_end);
+
+                let units_list = || {
+                    let names: Vec<&str> = units.iter().map(|u| u.unit.as_str()).collect();
+                    names.join(", ")
+                };
+                let range = units
+                    .iter()
+                    .find(|u| u.unit == unit)
+                    .ok_or_else(|| format!("does not end in one of {}", units_list()))?;
+                if !is_digits(number) {
+                    return Err("does not start with a whole number".to_string());
+                }
+
+                let number = number
+                    .parse::<i64>()
+                    .map_err(|_| "is too large".to_string())?;
+
+                check_range(number, range.min, range.max, &range.unit)
             }
         }
-        "eyr" => {
-            value.len() == 4 && {
-                let year = value.parse::<i32>().unwrap();
-                return year >= 2020 && year <= 2030;
+    }
+}
+
+impl Schema {
+    fn parse(rules: &str) -> Result<Self, String> {
+        let mut schema = Schema { rules: Vec::new() };
+
+        for (index, line) in rules.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let rule = FieldRule::parse(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+            if schema.rule(&rule.field).is_some() {
+                return Err(format!(
+                    "line {}: Duplicate rule for {}",
+                    index + 1,
+                    rule.field
+                ));
             }
+            schema.rules.push(rule);
         }
-        "hgt" => {
-            let captures = HEIGHT_REGEX.captures(value).unwrap();
-            let height: i32 = captures[1].parse::<i32>().unwrap();
-            let unit: &str = &captures[2];
 
-            match unit {
-                "in" => height >= 59 && height <= 76,
-                "cm" => height >= 150 && height <= 193,
-                _ => false,
+        Ok(schema)
+    }
+
+    fn rule(&self, field: &str) -> Option<&FieldRule> {
+        self.rules.iter().find(|rule| rule.field == field)
+    }
+
+    /// Fields without a rule are always valid.
+    fn check_field(&self, field: &str, value: &str) -> Result<(), String> {
+        match self.rule(field) {
+            Some(rule) => rule.field_type.check(value),
+            None => Ok(()),
+        }
+    }
+
+    fn required_fields(&self) -> impl Iterator<Item = &str> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.required)
+            .map(|rule| rule.field.as_str())
+    }
+}
+
+impl FieldRule {
+    fn parse(line: &str) -> Result<Self, String> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (field, required, type_name) = match tokens.as_slice() {
+            [field, required, type_name, ..] => (field, required, type_name),
+            _ => {
+                return Err(format!(
+                    "Expected <field> <required|optional> <type>, got {}",
+                    line
+                ))
+            }
+        };
+
+        let required = match *required {
+            "required" => true,
+            "optional" => false,
+            _ => return Err(format!("Expected required or optional, got {}", required)),
+        };
+
+        Ok(FieldRule {
+            field: field.to_string(),
+            required,
+            field_type: FieldType::parse(type_name, &tokens[3..])?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct InvalidField {
+    field: String,
+    value: String,
+    rule: String,
+}
+
+impl fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={} {}", self.field, self.value, self.rule)
+    }
+}
+
+/// The first and last line of a passport in the batch, starting at 1
+type LineSpan = RangeInclusive<usize>;
+
+/// Everything that's wrong with a single passport.
+/// Duplicate fields and fields without a rule are flagged, but don't make the passport invalid.
+/// When a field is repeated, its last value is the one that is validated.
+#[derive(Debug, PartialEq)]
+struct Verdict {
+    lines: LineSpan,
+    missing: Vec<String>,
+    invalid: Vec<InvalidField>,
+    duplicates: Vec<String>,
+    unknown: Vec<String>,
+}
+
+impl Verdict {
+    fn has_required_fields(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty()
+    }
+}
+
+fn check_passport(record: &Record, schema: &Schema) -> Verdict {
+    let mut fields: Vec<(&str, &str)> = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut unknown = Vec::new();
+
+    for (field, value) in record.fields.iter() {
+        let (field, value) = (field.as_str(), value.as_str());
+        match fields.iter_mut().find(|(f, _)| *f == field) {
+            Some(existing) => {
+                existing.1 = value;
+                if !duplicates.contains(&field.to_string()) {
+                    duplicates.push(field.to_string());
+                }
+            }
+            None => {
+                fields.push((field, value));
+                if schema.rule(field).is_none() {
+                    unknown.push(field.to_string());
+                }
             }
         }
-        "hcl" => {
-            if !value.starts_with("#") {
-                return false;
+    }
+
+    let missing = schema
+        .required_fields()
+        .filter(|required| fields.iter().all(|(field, _)| field != required))
+        .map(|field| field.to_string())
+        .collect();
+
+    let invalid = fields
+        .iter()
+        .filter_map(|(field, value)| {
+            let rule = schema.check_field(field, value).err()?;
+            Some(InvalidField {
+                field: field.to_string(),
+                value: value.to_string(),
+                rule,
+            })
+        })
+        .collect();
+
+    Verdict {
+        lines: record.lines.clone(),
+        missing,
+        invalid,
+        duplicates,
+        unknown,
+    }
+}
+
+/// A single passport from the batch, with its fields in the order they appear.
+#[derive(Debug, PartialEq)]
+struct Record {
+    lines: LineSpan,
+    fields: Vec<(String, String)>,
+}
+
+/// Reads passports one at a time from a batch, where passports are separated by blank lines.
+/// Lines can end in `\n` or `\r\n`, lines with only whitespace count as blank, and the batch doesn't need to end in a blank line.
+struct RecordReader<R: BufRead> {
+    reader: R,
+    line_number: usize,
+    line: String,
+}
+
+impl<R: BufRead> RecordReader<R> {
+    fn new(reader: R) -> Self {
+        RecordReader {
+            reader,
+            line_number: 0,
+            line: String::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for RecordReader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record: Option<Record> = None;
+
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return record.map(Ok),
+                Ok(_) => self.line_number += 1,
+                Err(e) => return Some(Err(e)),
             }
 
-            // Remove # from start
-            let value = crop_letters(value, 1);
-            value.len() == 6
-                && value.chars().all(|x| {
-                    if x.is_alphabetic() {
-                        return x <= 'f';
-                    }
-                    return x.is_digit(10);
+            if self.line.trim().is_empty() {
+                match record {
+                    Some(record) => return Some(Ok(record)),
+                    None => continue,
+                }
+            }
+
+            let record = record.get_or_insert_with(|| Record {
+                lines: self.line_number..=self.line_number,
+                fields: Vec::new(),
+            });
+            record.lines = *record.lines.start()..=self.line_number;
+
+            // A field without a `:` has an empty value
+            for passport_field in self.line.split_whitespace() {
+                let (field, value) = passport_field
+                    .split_once(':')
+                    .unwrap_or((passport_field, ""));
+                record.fields.push((field.to_string(), value.to_string()));
+            }
+        }
+    }
+}
+
+fn check_passports<R: BufRead>(reader: R, schema: &Schema) -> io::Result<Vec<Verdict>> {
+    RecordReader::new(reader)
+        .map(|record| record.map(|record| check_passport(&record, schema)))
+        .collect()
+}
+
+fn process<R: BufRead>(
+    reader: R,
+    schema: &Schema,
+    should_validate_fields: bool,
+) -> io::Result<i32> {
+    let mut count = 0;
+    for record in RecordReader::new(reader) {
+        let verdict = check_passport(&record?, schema);
+        let is_valid = match should_validate_fields {
+            true => verdict.is_valid(),
+            false => verdict.has_required_fields(),
+        };
+
+        if is_valid {
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+fn format_list(values: &[String]) -> String {
+    match values.is_empty() {
+        true => "-".to_string(),
+        false => values.join(", "),
+    }
+}
+
+/// Formats the verdicts as a table, with one row per passport.
+fn format_table(verdicts: &[Verdict]) -> String {
+    let mut rows: Vec<[String; 6]> = vec![[
+        "lines".to_string(),
+        "valid".to_string(),
+        "missing".to_string(),
+        "invalid".to_string(),
+        "duplicate".to_string(),
+        "unknown".to_string(),
+    ]];
+
+    for verdict in verdicts {
+        let invalid: Vec<String> = verdict.invalid.iter().map(|i| i.to_string()).collect();
+        rows.push([
+            format!("{}-{}", verdict.lines.start(), verdict.lines.end()),
+            if verdict.is_valid() { "yes" } else { "no" }.to_string(),
+            format_list(&verdict.missing),
+            format_list(&invalid),
+            format_list(&verdict.duplicates),
+            format_list(&verdict.unknown),
+        ]);
+    }
+
+    let mut widths = [0; 6];
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in rows.iter() {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+
+    table
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn json_list(values: &[String]) -> String {
+    let values: Vec<String> = values.iter().map(|v| json_string(v)).collect();
+    format!("[{}]", values.join(","))
+}
+
+/// Formats the verdicts as a JSON array, with one object per passport.
+fn format_json(verdicts: &[Verdict]) -> String {
+    let objects: Vec<String> = verdicts
+        .iter()
+        .map(|verdict| {
+            let invalid: Vec<String> = verdict
+                .invalid
+                .iter()
+                .map(|i| {
+                    format!(
+                        r#"{{"field":{},"value":{},"rule":{}}}"#,
+                        json_string(&i.field),
+                        json_string(&i.value),
+                        json_string(&i.rule)
+                    )
                 })
+                .collect();
+
+            format!(
+                r#"{{"lines":[{},{}],"valid":{},"missing":{},"invalid":[{}],"duplicates":{},"unknown":{}}}"#,
+                verdict.lines.start(),
+                verdict.lines.end(),
+                verdict.is_valid(),
+                json_list(&verdict.missing),
+                invalid.join(","),
+                json_list(&verdict.duplicates),
+                json_list(&verdict.unknown)
+            )
+        })
+        .collect();
+
+    format!("[{}]", objects.join(",\n"))
+}
+
+// Conversions
+// Passports can be converted from the batch format to CSV or JSON lines, and back.
+// * CSV has a column for every field in the Schema, in the order of the rules, followed by any other fields sorted by name.
+//   An empty cell means the field is missing, while a quoted empty cell `""` is a field without a value.
+//   A repeated field doesn't fit in a single cell, so passports with repeated fields can't be converted to CSV.
+// * JSON lines has an object per passport, with every field in the order it appears, including repeated fields.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Batch,
+    Csv,
+    JsonLines,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "batch" => Ok(Format::Batch),
+            "csv" => Ok(Format::Csv),
+            "jsonl" => Ok(Format::JsonLines),
+            _ => Err(format!(
+                "Invalid format {}, expected batch, csv or jsonl",
+                s
+            )),
         }
-        "ecl" => eye_colors.contains(&value.trim()),
-        "pid" => value.len() == 9,
-        _ => true,
-    }
-}
-
-fn validate_passport(passport: &str, should_validate_fields: bool) -> bool {
-    let passport_fields: Vec<&str> = passport.split_whitespace().collect();
-    let mut required_field_map: HashMap<&str, (i32, &str)> = [
-        ("byr", (0, "")),
-        ("iyr", (0, "")),
-        ("eyr", (0, "")),
-        ("hgt", (0, "")),
-        ("hcl", (0, "")),
-        ("ecl", (0, "")),
-        ("pid", (0, "")),
-    ]
-    .iter()
-    .cloned()
-    .collect();
-
-    for passport_field in passport_fields.iter() {
-        let passport_field_vec = passport_field.split(':').collect::<Vec<&str>>();
-        let field = passport_field_vec.get(0).unwrap();
-        let value = passport_field_vec.get(1).unwrap();
-        required_field_map.entry(field).and_modify(|e| {
-            e.0 = 1;
-            e.1 = value;
-        });
     }
+}
+
+fn to_batch(records: &[Record]) -> String {
+    let passports: Vec<String> = records
+        .iter()
+        .map(|record| {
+            let fields: Vec<String> = record
+                .fields
+                .iter()
+                .map(|(field, value)| format!("{}:{}", field, value))
+                .collect();
+            fields.join(" ")
+        })
+        .collect();
+
+    passports
+        .iter()
+        .map(|p| format!("{}\n", p))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn csv_columns(records: &[Record], schema: &Schema) -> Vec<String> {
+    let mut others: Vec<&str> = records
+        .iter()
+        .flat_map(|record| record.fields.iter())
+        .map(|(field, _)| field.as_str())
+        .filter(|field| schema.rule(field).is_none())
+        .collect();
+    others.sort_unstable();
+    others.dedup();
+
+    schema
+        .rules
+        .iter()
+        .map(|rule| rule.field.as_str())
+        .chain(others)
+        .map(|field| field.to_string())
+        .collect()
+}
+
+fn csv_cell(value: &str) -> String {
+    match value.is_empty() || value.contains([',', '"']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+fn to_csv(records: &[Record], schema: &Schema) -> Result<String, String> {
+    let columns = csv_columns(records, schema);
+    let mut csv = columns
+        .iter()
+        .map(|c| csv_cell(c))
+        .collect::<Vec<String>>()
+        .join(",");
+    csv.push('\n');
 
-    required_field_map.iter().all(|(field, (count, value))| {
-        if !should_validate_fields {
-            return count > &0;
+    for record in records {
+        let mut cells = Vec::with_capacity(columns.len());
+        for column in columns.iter() {
+            let mut values = record.fields.iter().filter(|(field, _)| field == column);
+            let value = values.next();
+            if values.next().is_some() {
+                return Err(format!(
+                    "line {}: Field {} is repeated, which can't be converted to CSV",
+                    record.lines.start(),
+                    column
+                ));
+            }
+            cells.push(value.map_or(String::new(), |(_, value)| csv_cell(value)));
         }
-        return count > &0 && validate_field(field, value);
-    })
+        csv.push_str(&cells.join(","));
+        csv.push('\n');
+    }
+
+    Ok(csv)
 }
 
-fn process(input: &str, should_validate_fields: bool) -> i32 {
-    let mut passports: Vec<String> = Vec::new();
-    let mut empty_index = 0;
-    let lines: Vec<&str> = input.lines().collect::<Vec<&str>>();
+/// Parses the cells of a CSV line, where an empty cell is None and a quoted empty cell is an empty string.
+fn parse_csv_line(line: &str) -> Result<Vec<Option<String>>, String> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut was_quoted = false;
+    let mut chars = line.chars().peekable();
 
-    for (index, line) in lines.iter().enumerate() {
-        if line.is_empty() {
-            let mut until = lines[empty_index..index].to_vec();
-            until.retain(|x| !x.is_empty());
-            passports.push(until.join(" "));
-            empty_index = index;
+    let mut end_cell = |cell: &mut String, was_quoted: &mut bool| {
+        let value = std::mem::take(cell);
+        cells.push(match value.is_empty() && !*was_quoted {
+            true => None,
+            false => Some(value),
+        });
+        *was_quoted = false;
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if cell.is_empty() && !was_quoted => {
+                quoted = true;
+                was_quoted = true;
+            }
+            ',' if !quoted => end_cell(&mut cell, &mut was_quoted),
+            c => cell.push(c),
         }
     }
 
-    passports.retain(|x| !x.is_empty());
+    if quoted {
+        return Err(format!("Unterminated quote in {}", line));
+    }
+    end_cell(&mut cell, &mut was_quoted);
 
-    let count = passports
+    Ok(cells)
+}
+
+fn from_csv(input: &str) -> Result<Vec<Record>, String> {
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let columns = match lines.next() {
+        Some((index, header)) => {
+            let columns = parse_csv_line(header)
+                .map_err(|e| format!("line {}: {}", index + 1, e))?
+                .into_iter()
+                .map(|column| column.filter(|c| !c.is_empty()))
+                .collect::<Option<Vec<String>>>()
+                .ok_or(format!("line {}: Empty column name", index + 1))?;
+
+            let mut unique_columns = HashSet::new();
+            if let Some(column) = columns.iter().find(|c| !unique_columns.insert(*c)) {
+                return Err(format!("line {}: Column {} is repeated", index + 1, column));
+            }
+            columns
+        }
+        None => return Ok(Vec::new()),
+    };
+
+    lines
+        .map(|(index, line)| {
+            let cells = parse_csv_line(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+            if cells.len() != columns.len() {
+                return Err(format!(
+                    "line {}: Expected {} cells, found {}",
+                    index + 1,
+                    columns.len(),
+                    cells.len()
+                ));
+            }
+
+            let fields = columns
+                .iter()
+                .zip(cells)
+                .filter_map(|(field, value)| Some((field.clone(), value?)))
+                .collect();
+
+            Ok(Record {
+                lines: index + 1..=index + 1,
+                fields,
+            })
+        })
+        .collect()
+}
+
+fn to_json_lines(records: &[Record]) -> String {
+    records
         .iter()
-        .filter(|x| validate_passport(x, should_validate_fields))
-        .count();
+        .map(|record| {
+            let fields: Vec<String> = record
+                .fields
+                .iter()
+                .map(|(field, value)| format!("{}:{}", json_string(field), json_string(value)))
+                .collect();
+            format!("{{{}}}\n", fields.join(","))
+        })
+        .collect()
+}
 
-    count as i32
+/// Parses a JSON string, starting at the opening quote.
+fn parse_json_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    if chars.next() != Some('"') {
+        return Err("Expected a string".to_string());
+    }
+
+    let mut string = String::new();
+    loop {
+        match chars.next().ok_or("Unterminated string")? {
+            '"' => return Ok(string),
+            '\\' => match chars.next().ok_or("Unterminated string")? {
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                'r' => string.push('\r'),
+                'b' => string.push('\u{8}'),
+                'f' => string.push('\u{c}'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let c = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(std::char::from_u32)
+                        .ok_or(format!("Invalid escape \\u{}", hex))?;
+                    string.push(c);
+                }
+                c @ ('"' | '\\' | '/') => string.push(c),
+                c => return Err(format!("Invalid escape \\{}", c)),
+            },
+            c => string.push(c),
+        }
+    }
+}
+
+/// Parses a JSON object with string values, keeping the fields in order.
+fn parse_json_object(line: &str) -> Result<Vec<(String, String)>, String> {
+    let mut chars = line.trim().chars().peekable();
+    let skip_whitespace = |chars: &mut Peekable<Chars>| {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+    };
+
+    if chars.next() != Some('{') {
+        return Err("Expected {".to_string());
+    }
+
+    let mut fields = Vec::new();
+    skip_whitespace(&mut chars);
+    if chars.peek() == Some(&'}') {
+        chars.next();
+    } else {
+        loop {
+            skip_whitespace(&mut chars);
+            let field = parse_json_string(&mut chars)?;
+            skip_whitespace(&mut chars);
+            if chars.next() != Some(':') {
+                return Err(format!("Expected : after {}", field));
+            }
+            skip_whitespace(&mut chars);
+            let value = parse_json_string(&mut chars)?;
+            fields.push((field, value));
+
+            skip_whitespace(&mut chars);
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => return Err("Expected , or }".to_string()),
+            }
+        }
+    }
+
+    match chars.next() {
+        None => Ok(fields),
+        Some(c) => Err(format!("Unexpected {} after the object", c)),
+    }
+}
+
+fn from_json_lines(input: &str) -> Result<Vec<Record>, String> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let fields =
+                parse_json_object(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+            Ok(Record {
+                lines: index + 1..=index + 1,
+                fields,
+            })
+        })
+        .collect()
+}
+
+fn convert<R: BufRead>(
+    mut reader: R,
+    from: Format,
+    to: Format,
+    schema: &Schema,
+) -> Result<String, String> {
+    let records = match from {
+        Format::Batch => RecordReader::new(reader)
+            .collect::<io::Result<Vec<Record>>>()
+            .map_err(|e| e.to_string())?,
+        Format::Csv | Format::JsonLines => {
+            let mut input = String::new();
+            reader
+                .read_to_string(&mut input)
+                .map_err(|e| e.to_string())?;
+            match from {
+                Format::Csv => from_csv(&input)?,
+                _ => from_json_lines(&input)?,
+            }
+        }
+    };
+
+    Ok(match to {
+        Format::Batch => to_batch(&records),
+        Format::Csv => to_csv(&records, schema)?,
+        Format::JsonLines => to_json_lines(&records),
+    })
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let filepath = args.get(1).expect("Input file cannot be empty!");
-    let input = fs::read_to_string(filepath).expect("Something went wrong while reading input");
+    let input = || {
+        let file = File::open(filepath).expect("Something went wrong while reading input");
+        BufReader::new(file)
+    };
+
+    // Usage: day-04 <input> [rules file] [--report table|json]
+    //        day-04 <input> [rules file] --convert <from> <to>, with the formats batch, csv and jsonl
+    let report = args
+        .iter()
+        .position(|arg| arg == "--report")
+        .map(|index| args.get(index + 1).map_or("table", String::as_str));
+
+    // An optional rules file replaces the rules from the puzzle
+    let rules_path = args.get(2).filter(|arg| !arg.starts_with("--"));
+    let custom_schema = rules_path.map(|rules_path| {
+        let rules =
+            fs::read_to_string(rules_path).expect("Something went wrong while reading rules");
+        Schema::parse(&rules).unwrap_or_else(|e| panic!("Invalid rules: {}", e))
+    });
+    let schema = custom_schema.as_ref().unwrap_or(&DEFAULT_SCHEMA);
+
+    if let Some(index) = args.iter().position(|arg| arg == "--convert") {
+        let format = |arg: Option<&String>| {
+            let format = arg.expect("Conversion needs a format to convert from and to!");
+            format.parse::<Format>().unwrap_or_else(|e| panic!("{}", e))
+        };
+        let (from, to) = (format(args.get(index + 1)), format(args.get(index + 2)));
+        print!(
+            "{}",
+            convert(input(), from, to, schema).unwrap_or_else(|e| panic!("{}", e))
+        );
+        return;
+    }
+
+    let verdicts =
+        || check_passports(input(), schema).expect("Something went wrong while reading input");
+    match report {
+        Some("table") => print!("{}", format_table(&verdicts())),
+        Some("json") => println!("{}", format_json(&verdicts())),
+        Some(format) => panic!("Invalid report format {}", format),
+        None => {}
+    }
 
     // -- Part 01 --
-    let valid = process(&input, false);
+    let valid = process(input(), schema, false).expect("Something went wrong while reading input");
     println!("Number of valid passports: {}", valid);
 
     // -- Part 02 --
-    let valid = process(&input, true);
-    println!("Number of valid passports after stricter validation: {}", valid);
+    let valid = process(input(), schema, true).expect("Something went wrong while reading input");
+    println!(
+        "Number of valid passports after stricter validation: {}",
+        valid
+    );
 }
 
 #[cfg(test)]
-
 mod tests {
     use super::*;
 
@@ -167,7 +984,10 @@ mod tests {
 
         "#;
 
-        assert_eq!(process(input, false), 2);
+        assert_eq!(
+            process(input.as_bytes(), &DEFAULT_SCHEMA, false).unwrap(),
+            2
+        );
     }
 
     #[test]
@@ -188,29 +1008,438 @@ iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719
 
         "#;
 
-        assert_eq!(process(input, true), 4);
+        assert_eq!(process(input.as_bytes(), &DEFAULT_SCHEMA, true).unwrap(), 4);
     }
 
     #[test]
-
     fn should_validate_fields() {
-        assert!(validate_field("byr", "2002"));
-        assert_eq!(validate_field("byr", "2003"), false);
+        assert!(DEFAULT_SCHEMA.check_field("byr", "2002").is_ok());
+        assert!(DEFAULT_SCHEMA.check_field("byr", "2003").is_err());
+
+        assert!(DEFAULT_SCHEMA.check_field("hgt", "60in").is_ok());
+        assert!(DEFAULT_SCHEMA.check_field("hgt", "190cm").is_ok());
+
+        assert!(DEFAULT_SCHEMA.check_field("hgt", "190in").is_err());
+        assert!(DEFAULT_SCHEMA.check_field("hgt", "190").is_err());
+
+        assert!(DEFAULT_SCHEMA.check_field("hcl", "#123abc").is_ok());
+        assert!(DEFAULT_SCHEMA.check_field("hcl", "#123abz").is_err());
+        assert!(DEFAULT_SCHEMA.check_field("hcl", "123abc").is_err());
+
+        assert!(DEFAULT_SCHEMA.check_field("ecl", "brn").is_ok());
+        assert!(DEFAULT_SCHEMA.check_field("ecl", "wat").is_err());
+
+        assert!(DEFAULT_SCHEMA.check_field("pid", "000000001").is_ok());
+        assert!(DEFAULT_SCHEMA.check_field("pid", "0123456789").is_err());
+    }
+
+    #[test]
+    fn should_validate_with_custom_schema() {
+        let schema = Schema::parse(
+            r#"
+# a library card
+num required regex ^\d{6}$
+exp required int 2020..2025
+lvl required enum gold silver
+ht optional unit 100..200cm
+"#,
+        )
+        .unwrap();
+
+        let input = "num:123456 exp:2021 lvl:gold\n\nnum:123456 exp:2026 lvl:gold ht:150cm\n\nnum:1 exp:2020\n\n";
+        assert_eq!(process(input.as_bytes(), &schema, false).unwrap(), 2);
+        assert_eq!(process(input.as_bytes(), &schema, true).unwrap(), 1);
+
+        assert!(schema.check_field("ht", "150cm").is_ok());
+        assert!(schema.check_field("ht", "150in").is_err());
+        assert!(schema.check_field("ht", "cm").is_err());
+        assert!(schema.check_field("xyz", "anything").is_ok());
+        assert_eq!(
+            schema.required_fields().collect::<Vec<&str>>(),
+            vec!["num", "exp", "lvl"]
+        );
+    }
+
+    #[test]
+    fn should_not_parse_invalid_rules() {
+        assert!(Schema::parse("byr required").is_err());
+        assert!(Schema::parse("byr needed any").is_err());
+        assert!(Schema::parse("byr required float 1..2").is_err());
+        assert!(Schema::parse("byr required int 2..1").is_err());
+        assert!(Schema::parse("byr required int 1..2 digits=x").is_err());
+        assert!(Schema::parse("hgt required unit 1..2").is_err());
+        assert!(Schema::parse("hcl required regex [").is_err());
+        assert!(Schema::parse("ecl required enum").is_err());
+        assert_eq!(
+            Schema::parse("byr required any\nbyr optional any").unwrap_err(),
+            "line 2: Duplicate rule for byr"
+        );
+        assert_eq!(DEFAULT_SCHEMA.rules.len(), 8);
+    }
+
+    #[test]
+    fn should_report_rule_broken() {
+        let check = |field, value| DEFAULT_SCHEMA.check_field(field, value);
+        assert_eq!(check("hgt", "190in"), Err("exceeds 76in".to_string()));
+        assert_eq!(check("hgt", "149cm"), Err("is below 150cm".to_string()));
+        assert_eq!(
+            check("hgt", "190"),
+            Err("does not end in one of cm, in".to_string())
+        );
+        assert_eq!(check("byr", "1919"), Err("is below 1920".to_string()));
+        assert_eq!(
+            check("byr", "20021"),
+            Err("does not have 4 digits".to_string())
+        );
+        assert_eq!(
+            check("iyr", "20x0"),
+            Err("is not a whole number".to_string())
+        );
+        assert!(check("ecl", "wat")
+            .unwrap_err()
+            .starts_with("is not one of amb"));
+        assert!(check("hcl", "123abc")
+            .unwrap_err()
+            .starts_with("does not match"));
+    }
+
+    #[test]
+    fn should_check_passports() {
+        let input = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 cid:147 hgt:190in\n\n\
+                     iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884 foo:bar\nhcl:#cfa07d byr:1929 byr:1930\n\n";
+        let verdicts = check_passports(input.as_bytes(), &DEFAULT_SCHEMA).unwrap();
+        assert_eq!(verdicts.len(), 2);
+
+        assert_eq!(verdicts[0].lines, 1..=2);
+        assert!(verdicts[0].has_required_fields());
+        assert!(!verdicts[0].is_valid());
+        assert_eq!(verdicts[0].invalid[0].to_string(), "hgt=190in exceeds 76in");
+
+        assert_eq!(verdicts[1].lines, 4..=5);
+        assert_eq!(verdicts[1].missing, vec!["hgt"]);
+        assert_eq!(verdicts[1].duplicates, vec!["byr"]);
+        assert_eq!(verdicts[1].unknown, vec!["foo"]);
+        assert!(verdicts[1].invalid.is_empty());
+
+        let table = format_table(&verdicts);
+        assert_eq!(
+            table.lines().next().unwrap(),
+            "lines  valid  missing  invalid                 duplicate  unknown"
+        );
+        assert_eq!(
+            table.lines().nth(1).unwrap(),
+            "1-2    no     -        hgt=190in exceeds 76in  -          -"
+        );
+        assert_eq!(
+            table.lines().nth(2).unwrap(),
+            "4-5    no     hgt      -                       byr        foo"
+        );
+
+        let json = format_json(&verdicts);
+        assert_eq!(
+            json.lines().next().unwrap(),
+            r#"[{"lines":[1,2],"valid":false,"missing":[],"invalid":[{"field":"hgt","value":"190in","rule":"exceeds 76in"}],"duplicates":[],"unknown":[]},"#
+        );
+        assert_eq!(json_string("a\"b\\\n"), r#""a\"b\\\u000a""#);
+    }
+
+    #[test]
+    fn should_read_records() {
+        let input =
+            "\r\n  \r\nbyr:1937 hgt\r\niyr:2017\r\n \t \r\n\r\necl:gry cid:1\n\npid:1\npid:2";
+        let records = RecordReader::new(input.as_bytes())
+            .collect::<io::Result<Vec<Record>>>()
+            .unwrap();
+
+        let field = |k: &str, v: &str| (k.to_string(), v.to_string());
+        assert_eq!(
+            records,
+            vec![
+                Record {
+                    lines: 3..=4,
+                    fields: vec![field("byr", "1937"), field("hgt", ""), field("iyr", "2017")]
+                },
+                Record {
+                    lines: 7..=7,
+                    fields: vec![field("ecl", "gry"), field("cid", "1")]
+                },
+                Record {
+                    lines: 9..=10,
+                    fields: vec![field("pid", "1"), field("pid", "2")]
+                },
+            ]
+        );
 
-        assert_eq!(validate_field("hgt", "60in"), true);
-        assert_eq!(validate_field("hgt", "190cm"), true);
+        assert_eq!(RecordReader::new("".as_bytes()).count(), 0);
+        assert_eq!(RecordReader::new("\n \n".as_bytes()).count(), 0);
+    }
 
-        assert_eq!(validate_field("hgt", "190in"), false);
-        assert_eq!(validate_field("hgt", "190"), false);
+    #[test]
+    fn should_count_trailing_passport() {
+        let input =
+            "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\r\nbyr:1937 iyr:2017 cid:147 hgt:183cm";
+        assert_eq!(process(input.as_bytes(), &DEFAULT_SCHEMA, true).unwrap(), 1);
+    }
 
-        assert_eq!(validate_field("hcl", "#123abc"), true);
-        assert_eq!(validate_field("hcl", "#123abz"), false);
-        assert_eq!(validate_field("hcl", "123abc"), false);
+    const BATCH: &str = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 cid:147 hgt:183cm\n\n\
+                         iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884 zzz:\"a,b\" aaa:x\nhcl:#cfa07d byr:1929\n";
+
+    fn read_records(input: &str) -> Vec<Record> {
+        RecordReader::new(input.as_bytes())
+            .collect::<io::Result<Vec<Record>>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn should_convert_to_csv_and_back() {
+        let records = read_records(BATCH);
+        let csv = to_csv(&records, &DEFAULT_SCHEMA).unwrap();
+        assert_eq!(
+            csv,
+            "byr,iyr,eyr,hgt,hcl,ecl,pid,cid,aaa,zzz\n\
+             1937,2017,2020,183cm,#fffffd,gry,860033327,147,,\n\
+             1929,2013,2023,,#cfa07d,amb,028048884,350,x,\"\"\"a,b\"\"\"\n"
+        );
+
+        let converted = from_csv(&csv).unwrap();
+        let fields = |record: &Record| {
+            let mut fields = record.fields.clone();
+            fields.sort();
+            fields
+        };
+        assert_eq!(converted.len(), 2);
+        for (converted, record) in converted.iter().zip(records.iter()) {
+            assert_eq!(fields(converted), fields(record));
+        }
 
-        assert_eq!(validate_field("ecl", "brn"), true);
-        assert_eq!(validate_field("ecl", "wat"), false);
+        // Converting back and forth again doesn't change anything.
+        let batch = to_batch(&converted);
+        assert_eq!(to_csv(&read_records(&batch), &DEFAULT_SCHEMA), Ok(csv));
+
+        assert!(from_csv("byr,iyr\n1937\n").is_err());
+        assert!(from_csv("byr\n\"1937\n").is_err());
+        assert!(from_csv("byr,byr\n1937,1938\n").is_err());
+        assert!(from_csv("byr,\n1937,\n").is_err());
+        assert_eq!(
+            parse_csv_line("a,\"b,\"\"c\",,\"\"").unwrap(),
+            vec![
+                Some("a".to_string()),
+                Some("b,\"c".to_string()),
+                None,
+                Some(String::new())
+            ]
+        );
+    }
+
+    #[test]
+    fn should_round_trip_empty_fields_through_csv() {
+        // hgt is present without a value, which makes the passport invalid rather than missing a field.
+        let batch =
+            "byr:1937 iyr:2017 eyr:2020 hgt: hcl:#fffffd ecl:gry pid:860033327\n\nbyr:1937 cid\n";
+        let records = read_records(batch);
+        let csv = to_csv(&records, &DEFAULT_SCHEMA).unwrap();
+        assert_eq!(
+            csv,
+            "byr,iyr,eyr,hgt,hcl,ecl,pid,cid\n\
+             1937,2017,2020,\"\",#fffffd,gry,860033327,\n\
+             1937,,,,,,,\"\"\n"
+        );
+
+        let converted = from_csv(&csv).unwrap();
+        assert_eq!(converted[0].fields[3], ("hgt".to_string(), String::new()));
+        assert_eq!(
+            converted[1].fields,
+            vec![
+                ("byr".to_string(), "1937".to_string()),
+                ("cid".to_string(), String::new())
+            ]
+        );
+
+        let before = check_passport(&records[0], &DEFAULT_SCHEMA);
+        let after = check_passport(&converted[0], &DEFAULT_SCHEMA);
+        assert!(after.missing.is_empty());
+        assert_eq!(after.invalid, before.invalid);
+        assert_eq!(to_csv(&converted, &DEFAULT_SCHEMA), Ok(csv));
+
+        // A repeated field can't be written to a single cell.
+        let repeated = read_records("byr:1937\n\nbyr:1937 hgt:1 byr:1938\n");
+        assert_eq!(
+            to_csv(&repeated, &DEFAULT_SCHEMA),
+            Err("line 3: Field byr is repeated, which can't be converted to CSV".to_string())
+        );
+        assert!(convert(
+            "byr:1937 byr:1938\n".as_bytes(),
+            Format::Batch,
+            Format::Csv,
+            &DEFAULT_SCHEMA
+        )
+        .is_err());
+    }
 
-        assert_eq!(validate_field("pid", "000000001"), true);
-        assert_eq!(validate_field("pid", "0123456789"), false);
+    #[test]
+    fn should_convert_to_json_lines_and_back() {
+        let records = read_records(BATCH);
+        let json = to_json_lines(&records);
+        assert_eq!(
+            json.lines().next().unwrap(),
+            r##"{"ecl":"gry","pid":"860033327","eyr":"2020","hcl":"#fffffd","byr":"1937","iyr":"2017","cid":"147","hgt":"183cm"}"##
+        );
+
+        let converted = from_json_lines(&json).unwrap();
+        for (converted, record) in converted.iter().zip(records.iter()) {
+            assert_eq!(converted.fields, record.fields);
+        }
+        assert_eq!(
+            to_batch(&converted),
+            BATCH.replace("\nbyr", " byr").replace("\nhcl", " hcl")
+        );
+
+        assert_eq!(
+            parse_json_object(r#" { "a" : "A\"\\\/" , "b":"" } "#).unwrap(),
+            vec![
+                ("a".to_string(), "A\"\\/".to_string()),
+                ("b".to_string(), String::new())
+            ]
+        );
+        assert_eq!(parse_json_object("{}").unwrap(), vec![]);
+        assert!(parse_json_object(r#"{"a":1}"#).is_err());
+        assert!(parse_json_object(r#"{"a":"b""#).is_err());
+        assert!(parse_json_object(r#"{"a":"b"} x"#).is_err());
+        assert!(from_json_lines("{}\n[]\n")
+            .unwrap_err()
+            .starts_with("line 2"));
+    }
+
+    #[test]
+    fn should_convert_between_formats() {
+        let csv = convert(
+            BATCH.as_bytes(),
+            Format::Batch,
+            Format::Csv,
+            &DEFAULT_SCHEMA,
+        )
+        .unwrap();
+        let json = convert(
+            csv.as_bytes(),
+            Format::Csv,
+            Format::JsonLines,
+            &DEFAULT_SCHEMA,
+        )
+        .unwrap();
+        let batch = convert(
+            json.as_bytes(),
+            Format::JsonLines,
+            Format::Batch,
+            &DEFAULT_SCHEMA,
+        )
+        .unwrap();
+
+        assert_eq!(
+            process(batch.as_bytes(), &DEFAULT_SCHEMA, false).unwrap(),
+            1
+        );
+        assert_eq!(read_records(&batch).len(), 2);
+        assert_eq!("jsonl".parse::<Format>(), Ok(Format::JsonLines));
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn should_validate_fields_exactly() {
+        let cases = [
+            // Boundaries from the puzzle
+            ("byr", "1920", true),
+            ("byr", "1919", false),
+            ("byr", "2002", true),
+            ("byr", "2003", false),
+            ("iyr", "2010", true),
+            ("iyr", "2009", false),
+            ("iyr", "2020", true),
+            ("iyr", "2021", false),
+            ("eyr", "2020", true),
+            ("eyr", "2019", false),
+            ("eyr", "2030", true),
+            ("eyr", "2031", false),
+            ("hgt", "150cm", true),
+            ("hgt", "149cm", false),
+            ("hgt", "193cm", true),
+            ("hgt", "194cm", false),
+            ("hgt", "59in", true),
+            ("hgt", "58in", false),
+            ("hgt", "76in", true),
+            ("hgt", "77in", false),
+            ("hcl", "#123abc", true),
+            ("hcl", "#000000", true),
+            ("ecl", "amb", true),
+            ("ecl", "oth", true),
+            ("pid", "000000001", true),
+            ("cid", "", true),
+            ("xyz", "anything", true),
+            // Malformed years
+            ("byr", "", false),
+            ("byr", "19a0", false),
+            ("byr", "abcd", false),
+            ("byr", "+192", false),
+            ("byr", "-192", false),
+            ("byr", " 1920", false),
+            ("byr", "01920", false),
+            ("byr", "١٩٢٠", false),
+            ("iyr", "2015.0", false),
+            ("eyr", "99999999999999999999", false),
+            // Malformed heights
+            ("hgt", "", false),
+            ("hgt", "190", false),
+            ("hgt", "cm", false),
+            ("hgt", "in", false),
+            ("hgt", "60 in", false),
+            ("hgt", "60IN", false),
+            ("hgt", "60inch", false),
+            ("hgt", "-60in", false),
+            ("hgt", "60.5in", false),
+            ("hgt", "99999999999999999999cm", false),
+            // Malformed hair colours
+            ("hcl", "", false),
+            ("hcl", "123abc", false),
+            ("hcl", "#123abz", false),
+            ("hcl", "#ABCDEF", false),
+            ("hcl", "#12345", false),
+            ("hcl", "#1234567", false),
+            ("hcl", "##12345", false),
+            ("hcl", "#123abc\n", false),
+            // Malformed eye colours
+            ("ecl", "", false),
+            ("ecl", "wat", false),
+            ("ecl", "BRN", false),
+            ("ecl", "brn ", false),
+            ("ecl", "amb,blu", false),
+            // Malformed passport ids
+            ("pid", "", false),
+            ("pid", "12345678", false),
+            ("pid", "0123456789", false),
+            ("pid", "00000000a", false),
+            ("pid", "abcdefghi", false),
+            ("pid", "+12345678", false),
+            ("pid", "٠١٢٣٤٥٦٧٨", false),
+        ];
+
+        for (field, value, expected) in cases.iter() {
+            assert_eq!(
+                DEFAULT_SCHEMA.check_field(field, value).is_ok(),
+                *expected,
+                "{}:{:?}",
+                field,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn should_match_whole_value_against_regex() {
+        let schema = Schema::parse("id required regex [a-z]+|[0-9]+").unwrap();
+        assert!(schema.check_field("id", "abc").is_ok());
+        assert!(schema.check_field("id", "123").is_ok());
+        assert_eq!(
+            schema.check_field("id", "abc123"),
+            Err("does not match [a-z]+|[0-9]+".to_string())
+        );
     }
 }
@@ -1,135 +1,574 @@
-#[macro_use]
-extern crate lazy_static;
-use regex::Regex;
-use std::{env, fs, process};
+use std::{env, fmt, fs, thread};
 
-#[derive(Debug)]
+// Day 05
+// Every boarding pass is a binary space partition of the seats on the plane: the first characters narrow down the row,
+// by picking the lower or upper half of the remaining rows, and the last characters narrow down the column the same way.
+// The seat id is the row times the number of columns plus the column.
+//
+// Since picking the lower half is a 0 and picking the upper half is a 1, the row and the column are binary numbers,
+// and with a power of two columns the whole boarding pass is the seat id written in binary, e.g. FBFBBFFRLR = 0101100101 = 357.
+// So a boarding pass is decoded in a single pass over its bytes, and large manifests are decoded in parallel.
+//
+// The number of rows and columns, and the letters for the lower and upper halves, are described by a PlaneLayout.
+// The default layout is the plane from the puzzle: 128 rows of 8 seats, with F/B for the rows and L/R for the columns.
+
+/// The most seats a layout can have, since the cabin keeps a count for every seat.
+const MAX_SEATS: i32 = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct Seat {
     row: i32,
     column: i32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct RangeDelimiters {
     upper: char,
     lower: char,
 }
 
-struct SeatRange {
-    start: i32,
-    end: i32,
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PlaneLayout {
+    rows: i32,
+    columns: i32,
+    row_delimiters: RangeDelimiters,
+    column_delimiters: RangeDelimiters,
 }
 
-const TOTAL_ROWS: i32 = 128;
-const TOTAL_COLUMNS: i32 = 7;
+impl Default for PlaneLayout {
+    fn default() -> Self {
+        PlaneLayout {
+            rows: 128,
+            columns: 8,
+            row_delimiters: RangeDelimiters {
+                upper: 'B',
+                lower: 'F',
+            },
+            column_delimiters: RangeDelimiters {
+                upper: 'R',
+                lower: 'L',
+            },
+        }
+    }
+}
 
-fn get_range_from_seat(seat: &str, delimeters: RangeDelimiters, max_count: i32) -> SeatRange {
-    let mut range = SeatRange {
-        start: 0,
-        end: max_count,
-    };
+/// Writes `value` as `bits` characters from the highest bit to the lowest, using the upper delimiter for a 1.
+fn push_bits(seat: &mut String, value: i32, bits: u32, delimiters: RangeDelimiters) {
+    for bit in (0..bits).rev() {
+        seat.push(if value >> bit & 1 == 1 {
+            delimiters.upper
+        } else {
+            delimiters.lower
+        });
+    }
+}
 
-    let RangeDelimiters { lower, upper } = delimeters;
+impl PlaneLayout {
+    /// Creates a layout with the given number of rows and columns, which both have to be a power of two,
+    /// with at most `MAX_SEATS` seats in total.
+    fn new(
+        rows: i32,
+        columns: i32,
+        row_delimiters: RangeDelimiters,
+        column_delimiters: RangeDelimiters,
+    ) -> Result<Self, String> {
+        for (name, count) in [("rows", rows), ("columns", columns)].iter() {
+            if *count <= 0 || count & (count - 1) != 0 {
+                return Err(format!(
+                    "Number of {} should be a power of two, got {}",
+                    name, count
+                ));
+            }
+        }
 
-    for char in seat.chars() {
-        if char == lower {
-            range.end = (range.start + range.end) / 2;
-        } else if char == upper {
-            range.start = range.start + (range.end - range.start) / 2;
+        if rows
+            .checked_mul(columns)
+            .is_none_or(|seats| seats > MAX_SEATS)
+        {
+            return Err(format!(
+                "Too many seats: {} rows of {}, at most {} seats are supported",
+                rows, columns, MAX_SEATS
+            ));
         }
+
+        for delimiters in [row_delimiters, column_delimiters].iter() {
+            if !delimiters.lower.is_ascii() || !delimiters.upper.is_ascii() {
+                return Err(format!(
+                    "Letters should be ASCII, got {}{}",
+                    delimiters.lower, delimiters.upper
+                ));
+            }
+
+            if delimiters.lower == delimiters.upper {
+                return Err(format!(
+                    "Lower and upper half both use {}",
+                    delimiters.lower
+                ));
+            }
+        }
+
+        Ok(PlaneLayout {
+            rows,
+            columns,
+            row_delimiters,
+            column_delimiters,
+        })
     }
 
-    range
-}
+    fn row_bits(&self) -> u32 {
+        self.rows.trailing_zeros()
+    }
 
-fn parse_seat(seat: &str) -> Result<Seat, String> {
-    lazy_static! {
-        static ref SEAT_REGEX: Regex = Regex::new(r"(\w{7})(\w{3})").unwrap();
+    fn column_bits(&self) -> u32 {
+        self.columns.trailing_zeros()
     }
 
-    if seat.len() < 10 {
-        return Err("Invalid seat".to_owned());
+    fn seat_count(&self) -> i32 {
+        self.rows * self.columns
     }
 
-    let captures = SEAT_REGEX.captures(seat).unwrap();
-    let rows: &str = &captures[1];
-    let columns: &str = &captures[2];
+    /// Decodes the seat id from a boarding pass, reading the lower half letters as 0 and the upper half letters as 1.
+    fn decode_id(&self, seat: &[u8]) -> Option<i32> {
+        let row_bits = self.row_bits() as usize;
+        if seat.len() != row_bits + self.column_bits() as usize {
+            return None;
+        }
+
+        let rows = (
+            self.row_delimiters.lower as u8,
+            self.row_delimiters.upper as u8,
+        );
+        let columns = (
+            self.column_delimiters.lower as u8,
+            self.column_delimiters.upper as u8,
+        );
+
+        let mut id = 0;
+        for (index, byte) in seat.iter().enumerate() {
+            let (lower, upper) = if index < row_bits { rows } else { columns };
+            let bit = match *byte {
+                b if b == lower => 0,
+                b if b == upper => 1,
+                _ => return None,
+            };
+            id = id << 1 | bit;
+        }
 
-    if rows.len() < 7 || columns.len() < 3 {
-        return Err("Invalid seat".to_owned());
+        Some(id)
     }
 
-    let row_range_delimiters = RangeDelimiters {
-        upper: 'B',
-        lower: 'F',
-    };
+    fn parse_seat(&self, seat: &str) -> Result<Seat, String> {
+        self.decode_id(seat.as_bytes())
+            .and_then(|id| self.seat_from_id(id))
+            .ok_or(format!("Invalid seat {}", seat))
+    }
 
-    let column_range_delimiters = RangeDelimiters {
-        upper: 'R',
-        lower: 'L',
-    };
+    fn get_seat_id(&self, seat: Seat) -> i32 {
+        seat.row * self.columns + seat.column
+    }
+
+    /// Returns the seat with the given id, if it's on the plane.
+    fn seat_from_id(&self, id: i32) -> Option<Seat> {
+        if !(0..self.seat_count()).contains(&id) {
+            return None;
+        }
+
+        Some(Seat {
+            row: id / self.columns,
+            column: id % self.columns,
+        })
+    }
+
+    /// Encodes a seat as a boarding pass, i.e. the inverse of `parse_seat`. Returns None for a seat that isn't on the plane.
+    fn encode_seat(&self, row: i32, column: i32) -> Option<String> {
+        if !(0..self.rows).contains(&row) || !(0..self.columns).contains(&column) {
+            return None;
+        }
 
-    let SeatRange {
-        start: seat_row, ..
-    } = get_range_from_seat(rows, row_range_delimiters, TOTAL_ROWS);
+        let mut seat = String::with_capacity((self.row_bits() + self.column_bits()) as usize);
+        push_bits(&mut seat, row, self.row_bits(), self.row_delimiters);
+        push_bits(
+            &mut seat,
+            column,
+            self.column_bits(),
+            self.column_delimiters,
+        );
+        Some(seat)
+    }
+}
 
-    let SeatRange {
-        end: seat_column, ..
-    } = get_range_from_seat(columns, column_range_delimiters, TOTAL_COLUMNS);
+/// Parses a layout written as `<rows>x<columns>`, optionally followed by the lower and upper letters for rows and for columns,
+/// e.g. `128x8 FB LR`
+fn parse_layout(layout: &[String]) -> Result<PlaneLayout, String> {
+    let default = PlaneLayout::default();
+    let size = layout.first().ok_or("Layout cannot be empty")?;
+    let (rows, columns) = size
+        .split_once('x')
+        .and_then(|(rows, columns)| Some((rows.parse::<i32>().ok()?, columns.parse::<i32>().ok()?)))
+        .ok_or(format!(
+            "Invalid layout {}, expected <rows>x<columns>",
+            size
+        ))?;
 
-    let seat = Seat {
-        row: seat_row,
-        column: seat_column,
+    let delimiters = |letters: Option<&String>, default: RangeDelimiters| match letters {
+        None => Ok(default),
+        Some(letters) => match letters.chars().collect::<Vec<char>>().as_slice() {
+            [lower, upper] => Ok(RangeDelimiters {
+                lower: *lower,
+                upper: *upper,
+            }),
+            _ => Err(format!(
+                "Invalid letters {}, expected <lower><upper>",
+                letters
+            )),
+        },
     };
 
-    Ok(seat)
+    PlaneLayout::new(
+        rows,
+        columns,
+        delimiters(layout.get(1), default.row_delimiters)?,
+        delimiters(layout.get(2), default.column_delimiters)?,
+    )
+}
+
+/// Where a vacant seat is, relative to the occupied seats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Vacancy {
+    /// In front of the first occupied seat, i.e. a seat that doesn't exist on this flight
+    Front,
+    /// Behind the last occupied seat
+    Back,
+    /// Between occupied seats
+    Interior,
+}
+
+/// Which seats on the plane are taken, and by how many boarding passes.
+struct Cabin {
+    layout: PlaneLayout,
+    passes: Vec<usize>,
+    /// The first and the last occupied seat, if any
+    occupied: Option<(i32, i32)>,
+}
+
+impl Cabin {
+    fn new(layout: PlaneLayout, ids: &[i32]) -> Self {
+        let mut passes = vec![0; layout.seat_count() as usize];
+        for id in ids {
+            passes[*id as usize] += 1;
+        }
+
+        let first = passes.iter().position(|passes| *passes > 0);
+        let last = passes.iter().rposition(|passes| *passes > 0);
+        let occupied = first
+            .zip(last)
+            .map(|(first, last)| (first as i32, last as i32));
+
+        Cabin {
+            layout,
+            passes,
+            occupied,
+        }
+    }
+
+    fn is_occupied(&self, id: i32) -> bool {
+        self.passes
+            .get(id as usize)
+            .is_some_and(|passes| *passes > 0)
+    }
+
+    fn last_occupied(&self) -> Option<i32> {
+        self.occupied.map(|(_, last)| last)
+    }
+
+    fn vacant_seats(&self) -> Vec<i32> {
+        (0..self.layout.seat_count())
+            .filter(|id| !self.is_occupied(*id))
+            .collect()
+    }
+
+    /// Classifies a vacant seat. A plane without any occupied seats only has seats in the front.
+    fn classify(&self, id: i32) -> Vacancy {
+        match self.occupied {
+            Some((first, _)) if id < first => Vacancy::Front,
+            Some((_, last)) if id > last => Vacancy::Back,
+            Some(_) => Vacancy::Interior,
+            None => Vacancy::Front,
+        }
+    }
+
+    /// Splits the vacant seats into the ones at the front, in the interior and at the back.
+    fn split_vacancies(&self) -> (Vec<i32>, Vec<i32>, Vec<i32>) {
+        let mut front = Vec::new();
+        let mut interior = Vec::new();
+        let mut back = Vec::new();
+
+        for id in self.vacant_seats() {
+            match self.classify(id) {
+                Vacancy::Front => front.push(id),
+                Vacancy::Interior => interior.push(id),
+                Vacancy::Back => back.push(id),
+            }
+        }
+
+        (front, interior, back)
+    }
+
+    /// Returns the rows at the front and at the back without any occupied seats.
+    fn missing_rows(&self) -> (Vec<i32>, Vec<i32>) {
+        let is_empty = |row: &i32| {
+            (0..self.layout.columns)
+                .all(|column| !self.is_occupied(row * self.layout.columns + column))
+        };
+        let front: Vec<i32> = (0..self.layout.rows).take_while(is_empty).collect();
+        let back: Vec<i32> = (front.len() as i32..self.layout.rows)
+            .rev()
+            .take_while(is_empty)
+            .collect();
+
+        (front, back.into_iter().rev().collect())
+    }
+
+    /// Your seat is the vacant one with occupied seats on both sides, i.e. ids one less and one more than yours.
+    fn is_your_seat(&self, id: i32) -> bool {
+        !self.is_occupied(id) && self.is_occupied(id - 1) && self.is_occupied(id + 1)
+    }
+
+    fn find_your_seat(&self) -> Vec<i32> {
+        (0..self.layout.seat_count())
+            .filter(|id| self.is_your_seat(*id))
+            .collect()
+    }
+
+    /// Returns the seats that are on more than one boarding pass, along with the number of passes.
+    fn duplicates(&self) -> Vec<(i32, usize)> {
+        self.passes
+            .iter()
+            .enumerate()
+            .filter(|(_, passes)| **passes > 1)
+            .map(|(id, passes)| (id as i32, *passes))
+            .collect()
+    }
+
+    /// Draws a row of the cabin on every line, prefixed with the row number:
+    /// `#` for an occupied seat, `!` for a seat on more than one pass, `X` for your seat, and `.` for any other vacant seat.
+    fn render(&self) -> String {
+        let width = (self.layout.rows - 1).to_string().len();
+
+        let mut map = String::new();
+        for row in 0..self.layout.rows {
+            map.push_str(&format!("{:>width$} ", row, width = width));
+            for column in 0..self.layout.columns {
+                let id = row * self.layout.columns + column;
+                map.push(match self.passes[id as usize] {
+                    0 if self.is_your_seat(id) => 'X',
+                    0 => '.',
+                    1 => '#',
+                    _ => '!',
+                });
+            }
+            map.push('\n');
+        }
+
+        map
+    }
 }
 
-fn get_seat_id(seat: Seat) -> i32 {
-    seat.row * 8 + seat.column
+/// The fewest lines worth decoding on a thread of their own.
+const MIN_CHUNK_LINES: usize = 256;
+
+#[derive(Debug, PartialEq)]
+struct LineError {
+    /// The line in the manifest, starting at 1
+    line: usize,
+    seat: String,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: Invalid seat {}", self.line, self.seat)
+    }
 }
 
-fn get_missing_id(mut ids: Vec<i32>) -> i32 {
-    ids.sort();
+/// Decodes the seat ids of a chunk of lines, where `first_line` is the line number of the first line in the chunk.
+/// Blank lines are skipped.
+fn decode_lines(
+    lines: &[&str],
+    first_line: usize,
+    layout: &PlaneLayout,
+) -> (Vec<i32>, Vec<LineError>) {
+    let mut ids = Vec::with_capacity(lines.len());
+    let mut errors = Vec::new();
 
-    let mut prev = ids[0];
-    let ids: Vec<i32> = ids[1..].to_vec();
-    for curr in ids {
-        if prev != curr - 1 {
-            break;
+    for (index, line) in lines.iter().enumerate() {
+        let seat = line.trim_end();
+        if seat.is_empty() {
+            continue;
+        }
+
+        match layout.decode_id(seat.as_bytes()) {
+            Some(id) => ids.push(id),
+            None => errors.push(LineError {
+                line: first_line + index,
+                seat: seat.to_string(),
+            }),
         }
-        prev += 1;
     }
-    prev + 1
+
+    (ids, errors)
 }
 
-fn process(input: &str) -> (i32, i32) {
-    let ids: Vec<i32> = input
-        .lines()
-        .map(|line| {
-            let seat = match parse_seat(line) {
-                Ok(seat) => seat,
-                Err(e) => {
-                    eprintln!("Error: {}", e);
-                    process::exit(1);
-                }
-            };
+/// Decodes every boarding pass in the manifest, splitting the lines between up to `threads` threads.
+/// The number of threads is capped by the available parallelism, and every thread gets at least `MIN_CHUNK_LINES` lines.
+/// Ids and errors are returned in the order of the lines.
+fn decode_manifest(
+    input: &str,
+    layout: &PlaneLayout,
+    threads: usize,
+) -> (Vec<i32>, Vec<LineError>) {
+    let lines: Vec<&str> = input.lines().collect();
+    let max_threads = thread::available_parallelism().map_or(1, |threads| threads.get()) * 4;
+    let threads = threads.clamp(1, max_threads);
+    let chunk_size = lines.len().div_ceil(threads).max(MIN_CHUNK_LINES);
 
-            get_seat_id(seat)
+    let decoded: Vec<(Vec<i32>, Vec<LineError>)> = thread::scope(|scope| {
+        let handles: Vec<_> = lines
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(index, chunk)| {
+                scope.spawn(move || decode_lines(chunk, index * chunk_size + 1, layout))
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Decoding thread panicked"))
+            .collect()
+    });
+
+    let mut ids = Vec::with_capacity(lines.len());
+    let mut errors = Vec::new();
+    for (chunk_ids, chunk_errors) in decoded {
+        ids.extend(chunk_ids);
+        errors.extend(chunk_errors);
+    }
+
+    (ids, errors)
+}
+
+fn process(input: &str, layout: &PlaneLayout) -> (Cabin, Vec<LineError>) {
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let (ids, errors) = decode_manifest(input, layout, threads);
+
+    (Cabin::new(*layout, &ids), errors)
+}
+
+/// Formats sorted numbers, joining consecutive numbers into ranges, e.g. `0-3, 5, 7-8`
+fn format_ranges(numbers: &[i32]) -> String {
+    let mut ranges: Vec<(i32, i32)> = Vec::new();
+    for number in numbers {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *number => *end = *number,
+            _ => ranges.push((*number, *number)),
+        }
+    }
+
+    let ranges: Vec<String> = ranges
+        .iter()
+        .map(|(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{}-{}", start, end),
         })
-        .collect::<Vec<i32>>();
+        .collect();
+    ranges.join(", ")
+}
+
+fn print_vacancies(cabin: &Cabin) {
+    let (front, back) = cabin.missing_rows();
+    println!("Missing rows at the front: {}", format_ranges(&front));
+    println!("Missing rows at the back: {}", format_ranges(&back));
+
+    let (front, interior, back) = cabin.split_vacancies();
+    for (vacancy, seats) in [
+        (Vacancy::Front, front),
+        (Vacancy::Interior, interior),
+        (Vacancy::Back, back),
+    ]
+    .iter()
+    {
+        println!(
+            "{:?} vacant seats ({}): {}",
+            vacancy,
+            seats.len(),
+            format_ranges(seats)
+        );
+    }
 
-    let max = *ids.iter().max().unwrap();
-    return (max, get_missing_id(ids));
+    for (id, passes) in cabin.duplicates() {
+        println!("Seat {} is on {} boarding passes", id, passes);
+    }
 }
 
 fn main() {
-    let args = env::args().collect::<Vec<String>>();
+    // Usage: day-05 <input> [--layout <rows>x<columns> [<row letters> <column letters>]]
+    //        day-05 encode <seat id> [--layout ...] prints the boarding pass for a seat
+    //        day-05 decode <boarding pass> [--layout ...] prints the seat on a boarding pass
+    //        day-05 <input> --map also draws the cabin
+    let mut args = env::args().collect::<Vec<String>>();
+    let show_map = args.iter().any(|arg| arg == "--map");
+    args.retain(|arg| arg != "--map");
+
+    let layout = match args.iter().position(|arg| arg == "--layout") {
+        Some(index) => {
+            let layout = parse_layout(&args[index + 1..]).unwrap_or_else(|e| panic!("{}", e));
+            args.truncate(index);
+            layout
+        }
+        None => PlaneLayout::default(),
+    };
+
+    if args.get(1).map(String::as_str) == Some("encode") {
+        let id = args.get(2).and_then(|id| id.parse::<i32>().ok());
+        let seat = id.and_then(|id| layout.seat_from_id(id));
+        match seat.and_then(|seat| layout.encode_seat(seat.row, seat.column)) {
+            Some(pass) => println!("{}", pass),
+            None => eprintln!("Error: Invalid seat id {:?}", args.get(2)),
+        }
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("decode") {
+        let pass = args.get(2).expect("Boarding pass cannot be empty!");
+        match layout.parse_seat(pass) {
+            Ok(seat) => println!(
+                "Row {}, column {}, seat id {}",
+                seat.row,
+                seat.column,
+                layout.get_seat_id(seat)
+            ),
+            Err(e) => eprintln!("Error: {}", e),
+        }
+        return;
+    }
+
     let filepath = args.get(1).expect("Input filepath cannot be empty!");
     let input = fs::read_to_string(filepath).expect("Something went wrong while reading the file");
 
-    let (max, seat_id) = process(&input);
-    println!("{:?} {}", max, seat_id);
+    let (cabin, errors) = process(&input, &layout);
+    for error in errors.iter() {
+        eprintln!("Error: {}", error);
+    }
+
+    if show_map {
+        print!("{}", cabin.render());
+    }
+
+    match cabin.last_occupied() {
+        Some(max) => println!("Highest seat id: {}", max),
+        None => println!("No boarding passes"),
+    }
+    println!("Your seat: {:?}", cabin.find_your_seat());
+    print_vacancies(&cabin);
 }
 
 #[cfg(test)]
@@ -138,8 +577,236 @@ mod tests {
 
     #[test]
     fn should_parse_seat() {
-        let seat = parse_seat("FBFBBFFRLR").unwrap();
+        let seat = PlaneLayout::default().parse_seat("FBFBBFFRLR").unwrap();
         assert_eq!(seat.row, 44);
         assert_eq!(seat.column, 5);
     }
+
+    #[test]
+    fn should_encode_seat() {
+        let layout = PlaneLayout::default();
+        assert_eq!(layout.encode_seat(44, 5), Some("FBFBBFFRLR".to_string()));
+        assert_eq!(layout.encode_seat(70, 7), Some("BFFFBBFRRR".to_string()));
+        assert_eq!(layout.encode_seat(14, 7), Some("FFFBBBFRRR".to_string()));
+        assert_eq!(layout.encode_seat(102, 4), Some("BBFFBBFRLL".to_string()));
+        assert_eq!(layout.encode_seat(0, 0), Some("FFFFFFFLLL".to_string()));
+        assert_eq!(layout.encode_seat(127, 7), Some("BBBBBBBRRR".to_string()));
+    }
+
+    #[test]
+    fn should_find_seat_from_id() {
+        let layout = PlaneLayout::default();
+        assert_eq!(layout.seat_from_id(357), Some(Seat { row: 44, column: 5 }));
+        assert_eq!(
+            layout.seat_from_id(820),
+            Some(Seat {
+                row: 102,
+                column: 4
+            })
+        );
+        assert_eq!(layout.seat_from_id(-1), None);
+        assert_eq!(layout.seat_from_id(1024), None);
+    }
+
+    #[test]
+    fn should_round_trip_every_seat() {
+        let small = PlaneLayout::new(
+            16,
+            4,
+            RangeDelimiters {
+                lower: 'a',
+                upper: 'b',
+            },
+            RangeDelimiters {
+                lower: '0',
+                upper: '1',
+            },
+        )
+        .unwrap();
+        let single_column =
+            PlaneLayout::new(8, 1, small.row_delimiters, small.column_delimiters).unwrap();
+
+        for layout in [PlaneLayout::default(), small, single_column].iter() {
+            for row in 0..layout.rows {
+                for column in 0..layout.columns {
+                    let pass = layout.encode_seat(row, column).unwrap();
+                    assert_eq!(
+                        layout.parse_seat(&pass),
+                        Ok(Seat { row, column }),
+                        "{}",
+                        pass
+                    );
+                }
+            }
+
+            for id in 0..layout.seat_count() {
+                let seat = layout.seat_from_id(id).unwrap();
+                let pass = layout.encode_seat(seat.row, seat.column).unwrap();
+                assert_eq!(layout.get_seat_id(layout.parse_seat(&pass).unwrap()), id);
+            }
+            assert_eq!(layout.seat_from_id(layout.seat_count()), None);
+        }
+    }
+
+    #[test]
+    fn should_not_encode_seat_off_the_plane() {
+        let layout = PlaneLayout::default();
+        assert_eq!(layout.encode_seat(128, 0), None);
+        assert_eq!(layout.encode_seat(0, 8), None);
+        assert_eq!(layout.encode_seat(-1, 0), None);
+
+        let small = parse_layout(&["16x4".to_string()]).unwrap();
+        assert_eq!(small.encode_seat(15, 3), Some("BBBBRR".to_string()));
+        assert_eq!(small.encode_seat(16, 0), None);
+        assert_eq!(small.encode_seat(0, 4), None);
+    }
+
+    #[test]
+    fn should_decode_with_layout() {
+        let layout =
+            parse_layout(&["16x4".to_string(), "UD".to_string(), "<>".to_string()]).unwrap();
+        assert_eq!(layout.parse_seat("DUDU><"), Ok(Seat { row: 10, column: 2 }));
+        assert_eq!(layout.get_seat_id(Seat { row: 10, column: 2 }), 42);
+        assert!(layout.parse_seat("FBFBBFFRLR").is_err());
+        assert!(layout.parse_seat("DUDU>").is_err());
+
+        assert!(PlaneLayout::default().parse_seat("FBFBBFFRL").is_err());
+        assert!(PlaneLayout::default().parse_seat("FBFBBFFRLRX").is_err());
+        assert!(PlaneLayout::default().parse_seat("FBFBBFFRLB").is_err());
+
+        assert!(parse_layout(&["12x8".to_string()]).is_err());
+        assert!(parse_layout(&["128x0".to_string()]).is_err());
+        assert!(parse_layout(&["128".to_string()]).is_err());
+        assert!(parse_layout(&["32768x32768".to_string()]).is_err());
+        assert!(parse_layout(&["2048x1024".to_string()]).is_err());
+        assert!(parse_layout(&["1024x1024".to_string()]).is_ok());
+        assert!(parse_layout(&["128x8".to_string(), "FF".to_string()]).is_err());
+        assert!(parse_layout(&["128x8".to_string(), "FBX".to_string()]).is_err());
+        assert_eq!(
+            parse_layout(&["128x8".to_string()]),
+            Ok(PlaneLayout::default())
+        );
+    }
+
+    #[test]
+    fn should_analyse_vacancies() {
+        let layout = PlaneLayout::new(
+            8,
+            4,
+            PlaneLayout::default().row_delimiters,
+            PlaneLayout::default().column_delimiters,
+        )
+        .unwrap();
+        // Rows 0 and 7 are missing, seat 13 is yours, 21-22 is a gap of two, and seat 18 is on two passes.
+        let ids: Vec<i32> = (6..28)
+            .filter(|id| *id != 13 && *id != 21 && *id != 22)
+            .chain(vec![18])
+            .collect();
+        let cabin = Cabin::new(layout, &ids);
+
+        assert_eq!(cabin.occupied, Some((6, 27)));
+        assert_eq!(cabin.last_occupied(), Some(27));
+        assert_eq!(cabin.find_your_seat(), vec![13]);
+        assert_eq!(cabin.duplicates(), vec![(18, 2)]);
+        assert_eq!(cabin.missing_rows(), (vec![0], vec![7]));
+
+        assert_eq!(cabin.vacant_seats().len(), 32 - 22 + 3);
+        assert_eq!(cabin.classify(13), Vacancy::Interior);
+        assert_eq!(
+            cabin.split_vacancies(),
+            (
+                vec![0, 1, 2, 3, 4, 5],
+                vec![13, 21, 22],
+                vec![28, 29, 30, 31]
+            )
+        );
+
+        assert_eq!(
+            cabin.render(),
+            "0 ....\n1 ..##\n2 ####\n3 #X##\n4 ##!#\n5 #..#\n6 ####\n7 ....\n"
+        );
+    }
+
+    #[test]
+    fn should_analyse_empty_cabin() {
+        let cabin = Cabin::new(PlaneLayout::default(), &[]);
+        assert_eq!(cabin.last_occupied(), None);
+        assert!(cabin.find_your_seat().is_empty());
+        assert_eq!(cabin.vacant_seats().len(), 1024);
+        assert_eq!(cabin.classify(0), Vacancy::Front);
+        assert_eq!(cabin.missing_rows().0.len(), 128);
+        assert!(cabin.missing_rows().1.is_empty());
+    }
+
+    #[test]
+    fn should_process_passes() {
+        let (cabin, errors) = process(
+            "FBFBBFFRLR\nFBFBBFFRRL\nFBFBBFFRRR\nFBFBBFBLLL\nFBFBBFBLRL\n",
+            &PlaneLayout::default(),
+        );
+        assert!(errors.is_empty());
+        assert_eq!(cabin.last_occupied(), Some(362));
+        assert_eq!(cabin.find_your_seat(), vec![361]);
+    }
+
+    #[test]
+    fn should_format_ranges() {
+        assert_eq!(format_ranges(&[0, 1, 2, 3, 5, 7, 8]), "0-3, 5, 7-8");
+        assert_eq!(format_ranges(&[]), "");
+    }
+
+    #[test]
+    fn should_decode_id_from_bytes() {
+        let layout = PlaneLayout::default();
+        assert_eq!(layout.decode_id(b"FBFBBFFRLR"), Some(357));
+        assert_eq!(layout.decode_id(b"BBFFBBFRLL"), Some(820));
+        assert_eq!(layout.decode_id(b"FBFBBFFRL"), None);
+        assert_eq!(layout.decode_id(b"FBFBBFFRLB"), None);
+        assert_eq!(layout.decode_id(b"RBFBBFFRLR"), None);
+        assert_eq!(layout.decode_id("FBFBBFFRLÉ".as_bytes()), None);
+
+        assert!(PlaneLayout::new(
+            128,
+            8,
+            RangeDelimiters {
+                lower: 'É',
+                upper: 'B'
+            },
+            layout.column_delimiters
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn should_decode_manifest_in_parallel() {
+        let layout = PlaneLayout::default();
+        let mut manifest = String::new();
+        let mut expected_ids = Vec::new();
+        for line in 0..5000 {
+            if line % 997 == 0 {
+                manifest.push_str("FBFBXFFRLR\r\n");
+                continue;
+            }
+
+            let id = (line * 7) % layout.seat_count();
+            let seat = layout.seat_from_id(id).unwrap();
+            manifest.push_str(&layout.encode_seat(seat.row, seat.column).unwrap());
+            manifest.push_str(if line % 2 == 0 { "\n" } else { "\r\n" });
+            expected_ids.push(id);
+        }
+        manifest.push('\n');
+
+        for threads in [0, 1, 3, 8, 64].iter() {
+            let (ids, errors) = decode_manifest(&manifest, &layout, *threads);
+            assert_eq!(ids, expected_ids);
+            assert_eq!(
+                errors.iter().map(|e| e.line).collect::<Vec<usize>>(),
+                vec![1, 998, 1995, 2992, 3989, 4986]
+            );
+        }
+
+        let (_, errors) = decode_manifest("FBFBBFFRLR\nnope\n", &layout, 2);
+        assert_eq!(errors[0].to_string(), "line 2: Invalid seat nope");
+        assert_eq!(decode_manifest("", &layout, 4), (vec![], vec![]));
+    }
 }
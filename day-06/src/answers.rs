@@ -0,0 +1,339 @@
+use std::ops::{BitAnd, BitOr, BitXor};
+use std::str::FromStr;
+
+// Customs declaration answers as 26-bit masks.
+
+// Every question is a letter from a to z, so the questions a person answered "yes" to fit in the lowest 26 bits of a u32,
+// with bit 0 for `a` and bit 25 for `z`. Set operations on answers are then single bitwise operations,
+// and counting answers is counting ones.
+
+/// The questions that one or more people answered "yes" to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Answers(u32);
+
+impl Answers {
+    pub const NONE: Answers = Answers(0);
+    pub const ALL: Answers = Answers((1 << 26) - 1);
+
+    fn bit(question: char) -> Option<u32> {
+        match question {
+            'a'..='z' => Some(1 << (question as u32 - 'a' as u32)),
+            _ => None,
+        }
+    }
+
+    pub fn contains(self, question: char) -> bool {
+        Answers::bit(question).is_some_and(|bit| self.0 & bit != 0)
+    }
+
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Returns the questions in alphabetical order.
+    pub fn questions(self) -> impl Iterator<Item = char> {
+        ('a'..='z').filter(move |question| self.contains(*question))
+    }
+}
+
+impl FromStr for Answers {
+    type Err = String;
+
+    /// Parses the answers of a single person, e.g. `abc`. Answering a question more than once is allowed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars().try_fold(Answers::NONE, |answers, question| {
+            let bit = Answers::bit(question).ok_or(format!("Invalid question {}", question))?;
+            Ok(Answers(answers.0 | bit))
+        })
+    }
+}
+
+/// Union: questions answered by either.
+impl BitOr for Answers {
+    type Output = Answers;
+
+    fn bitor(self, other: Answers) -> Answers {
+        Answers(self.0 | other.0)
+    }
+}
+
+/// Intersection: questions answered by both.
+impl BitAnd for Answers {
+    type Output = Answers;
+
+    fn bitand(self, other: Answers) -> Answers {
+        Answers(self.0 & other.0)
+    }
+}
+
+/// Symmetric difference: questions answered by exactly one of the two.
+impl BitXor for Answers {
+    type Output = Answers;
+
+    fn bitxor(self, other: Answers) -> Answers {
+        Answers(self.0 ^ other.0)
+    }
+}
+
+/// A question to ask about the answers of a group.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Query {
+    /// Questions answered by anyone in the group
+    Anyone,
+    /// Questions answered by everyone in the group
+    Everyone,
+    /// Questions answered by an odd number of people, i.e. the symmetric difference of everyone's answers
+    SymmetricDifference,
+    /// Questions answered by at least this many people
+    AtLeast(usize),
+    /// Questions answered by at least this percentage of the group
+    AtLeastPercent(u32),
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    /// Parses `anyone`, `everyone`, `xor`, `at-least:<k>` or `at-least:<p>%`, where k is at least 1 and p is between 1 and 100.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid query {}", s);
+
+        match s {
+            "anyone" => Ok(Query::Anyone),
+            "everyone" => Ok(Query::Everyone),
+            "xor" => Ok(Query::SymmetricDifference),
+            _ => {
+                let at_least = s.strip_prefix("at-least:").ok_or_else(invalid)?;
+                match at_least.strip_suffix('%') {
+                    Some(percent) => match percent.parse::<u32>() {
+                        Ok(percent) if (1..=100).contains(&percent) => {
+                            Ok(Query::AtLeastPercent(percent))
+                        }
+                        _ => Err(invalid()),
+                    },
+                    None => match at_least.parse::<usize>() {
+                        Ok(k) if k >= 1 => Ok(Query::AtLeast(k)),
+                        _ => Err(invalid()),
+                    },
+                }
+            }
+        }
+    }
+}
+
+/// The answers of every person in a group.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Group {
+    people: Vec<Answers>,
+}
+
+impl Group {
+    pub fn new(people: Vec<Answers>) -> Self {
+        Group { people }
+    }
+
+    pub fn len(&self) -> usize {
+        self.people.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.people.is_empty()
+    }
+
+    pub fn anyone(&self) -> Answers {
+        self.people
+            .iter()
+            .fold(Answers::NONE, |acc, answers| acc | *answers)
+    }
+
+    /// An empty group has no answers in common.
+    pub fn everyone(&self) -> Answers {
+        match self.people.is_empty() {
+            true => Answers::NONE,
+            false => self
+                .people
+                .iter()
+                .fold(Answers::ALL, |acc, answers| acc & *answers),
+        }
+    }
+
+    pub fn symmetric_difference(&self) -> Answers {
+        self.people
+            .iter()
+            .fold(Answers::NONE, |acc, answers| acc ^ *answers)
+    }
+
+    /// Returns the number of people that answered each question, from `a` to `z`.
+    pub fn question_counts(&self) -> [usize; 26] {
+        let mut counts = [0; 26];
+        for answers in self.people.iter() {
+            for (bit, count) in counts.iter_mut().enumerate() {
+                *count += (answers.0 >> bit & 1) as usize;
+            }
+        }
+
+        counts
+    }
+
+    /// Returns the questions answered by at least `k` people. Every question is answered by at least 0 people.
+    pub fn at_least(&self, k: usize) -> Answers {
+        let counts = self.question_counts();
+        let mask = (0..26)
+            .filter(|bit| counts[*bit] >= k)
+            .fold(0, |mask, bit| mask | 1 << bit);
+
+        Answers(mask)
+    }
+
+    /// Returns the questions answered by at least `percent`% of the group, rounding the number of people up.
+    pub fn at_least_percent(&self, percent: u32) -> Answers {
+        if self.people.is_empty() {
+            return Answers::NONE;
+        }
+
+        let people = (percent as usize * self.people.len()).div_ceil(100);
+        self.at_least(people)
+    }
+
+    pub fn query(&self, query: Query) -> Answers {
+        match query {
+            Query::Anyone => self.anyone(),
+            Query::Everyone => self.everyone(),
+            Query::SymmetricDifference => self.symmetric_difference(),
+            Query::AtLeast(k) => self.at_least(k),
+            Query::AtLeastPercent(percent) => self.at_least_percent(percent),
+        }
+    }
+}
+
+/// Parses groups of answers, with one person per line and groups separated by blank lines.
+pub fn parse_groups(input: &str) -> Result<Vec<Group>, String> {
+    let mut groups = Vec::new();
+    let mut group = Group::default();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            if !group.is_empty() {
+                groups.push(std::mem::take(&mut group));
+            }
+            continue;
+        }
+
+        let answers = line
+            .parse::<Answers>()
+            .map_err(|e| format!("line {}: {}", index + 1, e))?;
+        group.people.push(answers);
+    }
+
+    if !group.is_empty() {
+        groups.push(group);
+    }
+
+    Ok(groups)
+}
+
+/// Returns the sum of the number of questions matching the query in every group.
+pub fn sum_counts(groups: &[Group], query: Query) -> u32 {
+    groups.iter().map(|group| group.query(query).count()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(people: &[&str]) -> Group {
+        Group::new(people.iter().map(|p| p.parse().unwrap()).collect())
+    }
+
+    #[test]
+    fn should_parse_answers() {
+        let answers = "zaba".parse::<Answers>().unwrap();
+        assert_eq!(answers.count(), 3);
+        assert_eq!(answers.questions().collect::<String>(), "abz");
+        assert!(answers.contains('z'));
+        assert!(!answers.contains('c'));
+        assert!(!answers.contains('A'));
+
+        assert_eq!("".parse::<Answers>(), Ok(Answers::NONE));
+        assert_eq!(Answers::ALL.count(), 26);
+        assert!("abC".parse::<Answers>().is_err());
+    }
+
+    #[test]
+    fn should_combine_answers() {
+        let ab = "ab".parse::<Answers>().unwrap();
+        let bc = "bc".parse::<Answers>().unwrap();
+        assert_eq!((ab | bc).questions().collect::<String>(), "abc");
+        assert_eq!((ab & bc).questions().collect::<String>(), "b");
+        assert_eq!((ab ^ bc).questions().collect::<String>(), "ac");
+    }
+
+    #[test]
+    fn should_query_group() {
+        let group = group(&["abc", "abd", "ae", "a"]);
+        let query = |query| group.query(query).questions().collect::<String>();
+
+        assert_eq!(query(Query::Anyone), "abcde");
+        assert_eq!(query(Query::Everyone), "a");
+        // a is answered 4 times, b twice, c, d and e once
+        assert_eq!(query(Query::SymmetricDifference), "cde");
+        assert_eq!(query(Query::AtLeast(2)), "ab");
+        assert_eq!(query(Query::AtLeast(5)), "");
+        assert_eq!(query(Query::AtLeastPercent(50)), "ab");
+        assert_eq!(query(Query::AtLeastPercent(51)), "a");
+        assert_eq!(query(Query::AtLeastPercent(100)), "a");
+        assert_eq!(query(Query::AtLeastPercent(1)), "abcde");
+
+        assert_eq!(group.question_counts()[..5], [4, 2, 1, 1, 1]);
+        assert_eq!(group.at_least(0), Answers::ALL);
+    }
+
+    #[test]
+    fn should_query_empty_group() {
+        let group = Group::default();
+        assert_eq!(group.anyone(), Answers::NONE);
+        assert_eq!(group.everyone(), Answers::NONE);
+        assert_eq!(group.symmetric_difference(), Answers::NONE);
+        assert_eq!(group.at_least_percent(50), Answers::NONE);
+    }
+
+    #[test]
+    fn should_parse_queries() {
+        assert_eq!("anyone".parse::<Query>(), Ok(Query::Anyone));
+        assert_eq!("everyone".parse::<Query>(), Ok(Query::Everyone));
+        assert_eq!("xor".parse::<Query>(), Ok(Query::SymmetricDifference));
+        assert_eq!("at-least:3".parse::<Query>(), Ok(Query::AtLeast(3)));
+        assert_eq!(
+            "at-least:75%".parse::<Query>(),
+            Ok(Query::AtLeastPercent(75))
+        );
+
+        for invalid in [
+            "",
+            "all",
+            "at-least:",
+            "at-least:0",
+            "at-least:-1",
+            "at-least:0%",
+            "at-least:101%",
+            "at-least:x%",
+        ]
+        .iter()
+        {
+            assert!(invalid.parse::<Query>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn should_parse_groups() {
+        let groups = parse_groups("\r\nab\r\nac\r\n  \r\n\r\nb\nz").unwrap();
+        assert_eq!(groups, vec![group(&["ab", "ac"]), group(&["b", "z"])]);
+        assert_eq!(groups[0].len(), 2);
+
+        assert!(parse_groups("").unwrap().is_empty());
+        assert_eq!(
+            parse_groups("ab\n\na1").unwrap_err(),
+            "line 3: Invalid question 1"
+        );
+    }
+}
@@ -0,0 +1,697 @@
+use std::collections::{HashMap, HashSet};
+use std::{env, fs};
+
+// Day 20: Jurassic Jigsaw
+// https://adventofcode.com/2020/day/20
+
+// Every tile is a square of `#` and `.` pixels, which has been randomly rotated and flipped.
+// The tiles need to be arranged into a square image, such that the borders of adjacent tiles line up.
+
+// Algorithm
+// * Parse every tile into a Grid, and index each of its edges by a signature that is the same whichever way the edge is read.
+// * A signature that is shared by exactly one tile lies on the outer border of the image.
+//   Corner tiles are the ones that have two such unmatched edges (Part 01).
+// * Starting with a corner at the top-left, fill the image row by row. For every position only the tiles that share
+//   a signature with the left (or top) neighbour are candidates, and each candidate is tried in all 8 orientations.
+//   If a position can't be filled, backtrack and try the next orientation / candidate.
+// * Strip the borders of every tile and stitch them together into the actual image.
+// * Search the image for sea monsters in every orientation, the water roughness is the number of `#` that aren't
+//   part of any sea monster (Part 02).
+
+const PIXEL_ON: char = '#';
+const SEA_MONSTER_PIXEL: char = 'O';
+
+const SEA_MONSTER: [&str; 3] = [
+    "                  # ",
+    "#    ##    ##    ###",
+    " #  #  #  #  #  #   ",
+];
+
+type TileId = u64;
+type Grid = Vec<Vec<char>>;
+
+/// Maps an edge signature to the ids of all the tiles that have an edge with that signature.
+type EdgeIndex = HashMap<u32, Vec<TileId>>;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Tile {
+    id: TileId,
+    pixels: Grid,
+}
+
+fn parse_tile(block: &str) -> Result<Tile, String> {
+    let mut lines = block.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+    let header = lines.next().ok_or("Empty tile")?;
+
+    let id = header
+        .strip_prefix("Tile ")
+        .and_then(|h| h.strip_suffix(':'))
+        .and_then(|id| id.parse::<TileId>().ok())
+        .ok_or(format!("Invalid tile header {}", header))?;
+
+    let pixels: Grid = lines.map(|l| l.chars().collect()).collect();
+    if pixels.is_empty() || pixels.iter().any(|row| row.len() != pixels.len()) {
+        return Err(format!("Tile {} is not a square", id));
+    }
+
+    Ok(Tile { id, pixels })
+}
+
+fn parse_tiles(input: &str) -> Result<Vec<Tile>, String> {
+    input
+        .replace("\r\n", "\n")
+        .split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .map(parse_tile)
+        .collect()
+}
+
+// -- Grid Transformations --
+
+/// Rotates a square grid 90 degrees clockwise.
+fn rotate(grid: &[Vec<char>]) -> Grid {
+    let size = grid.len();
+    (0..size)
+        .map(|col| (0..size).rev().map(|row| grid[row][col]).collect())
+        .collect()
+}
+
+/// Flips a grid horizontally.
+fn flip(grid: &[Vec<char>]) -> Grid {
+    grid.iter()
+        .map(|row| row.iter().rev().cloned().collect())
+        .collect()
+}
+
+/// Returns all the 8 orientations of a grid, i.e. the 4 rotations of the grid and the 4 rotations of its flip.
+fn orientations(grid: &[Vec<char>]) -> Vec<Grid> {
+    let mut result = Vec::with_capacity(8);
+    for start in [grid.to_vec(), flip(grid)].iter() {
+        let mut current = start.clone();
+        for _ in 0..4 {
+            let next = rotate(&current);
+            result.push(current);
+            current = next;
+        }
+    }
+
+    result
+}
+
+// -- Edges --
+
+fn top_edge(grid: &[Vec<char>]) -> Vec<char> {
+    grid[0].clone()
+}
+
+fn bottom_edge(grid: &[Vec<char>]) -> Vec<char> {
+    grid[grid.len() - 1].clone()
+}
+
+fn left_edge(grid: &[Vec<char>]) -> Vec<char> {
+    grid.iter().map(|row| row[0]).collect()
+}
+
+fn right_edge(grid: &[Vec<char>]) -> Vec<char> {
+    grid.iter().map(|row| row[row.len() - 1]).collect()
+}
+
+fn edges(grid: &[Vec<char>]) -> [Vec<char>; 4] {
+    [
+        top_edge(grid),
+        right_edge(grid),
+        bottom_edge(grid),
+        left_edge(grid),
+    ]
+}
+
+/// Returns a signature for an edge, which doesn't depend on the direction the edge is read in.
+/// The edge is read as a binary number both ways, and the smaller of the two is the signature.
+fn edge_signature(edge: &[char]) -> u32 {
+    let to_bits = |acc: u32, pixel: &char| (acc << 1) | (*pixel == PIXEL_ON) as u32;
+    let forward = edge.iter().fold(0, to_bits);
+    let backward = edge.iter().rev().fold(0, to_bits);
+
+    forward.min(backward)
+}
+
+fn build_edge_index(tiles: &[Tile]) -> EdgeIndex {
+    let mut index = EdgeIndex::new();
+    for tile in tiles {
+        for edge in edges(&tile.pixels).iter() {
+            index.entry(edge_signature(edge)).or_default().push(tile.id);
+        }
+    }
+
+    index
+}
+
+/// Returns the number of edges of a tile that don't match the edge of any other tile.
+fn count_unmatched_edges(tile: &Tile, index: &EdgeIndex) -> usize {
+    edges(&tile.pixels)
+        .iter()
+        .filter(|edge| index[&edge_signature(edge)].len() == 1)
+        .count()
+}
+
+fn find_corners(tiles: &[Tile], index: &EdgeIndex) -> Vec<TileId> {
+    tiles
+        .iter()
+        .filter(|tile| count_unmatched_edges(tile, index) == 2)
+        .map(|tile| tile.id)
+        .collect()
+}
+
+// Part 01
+fn get_corner_product(tiles: &[Tile]) -> u64 {
+    let index = build_edge_index(tiles);
+    find_corners(tiles, &index).iter().product()
+}
+
+// -- Assembly --
+
+struct Assembler<'a> {
+    side: usize,
+    index: EdgeIndex,
+    orientations: HashMap<TileId, Vec<Grid>>,
+    tiles: &'a [Tile],
+}
+
+impl<'a> Assembler<'a> {
+    fn new(tiles: &'a [Tile]) -> Result<Self, String> {
+        let side = (tiles.len() as f64).sqrt() as usize;
+        if side == 0 || side * side != tiles.len() {
+            return Err(format!("{} tiles can't form a square image", tiles.len()));
+        }
+
+        let orientations = tiles
+            .iter()
+            .map(|tile| (tile.id, orientations(&tile.pixels)))
+            .collect();
+
+        Ok(Assembler {
+            side,
+            index: build_edge_index(tiles),
+            orientations,
+            tiles,
+        })
+    }
+
+    /// Checks whether the given orientation of a tile fits at `position`, given the tiles that were already placed.
+    fn fits(&self, position: usize, pixels: &[Vec<char>], placed: &[Tile]) -> bool {
+        let (row, col) = (position / self.side, position % self.side);
+        if col > 0 && right_edge(&placed[position - 1].pixels) != left_edge(pixels) {
+            return false;
+        }
+
+        if row > 0 && bottom_edge(&placed[position - self.side].pixels) != top_edge(pixels) {
+            return false;
+        }
+
+        true
+    }
+
+    /// Returns the ids of the tiles that could go at `position`, based on the edge of the left or the top neighbour.
+    fn candidates(&self, position: usize, placed: &[Tile]) -> Vec<TileId> {
+        let col = position % self.side;
+        let edge = if col > 0 {
+            right_edge(&placed[position - 1].pixels)
+        } else {
+            bottom_edge(&placed[position - self.side].pixels)
+        };
+
+        self.index
+            .get(&edge_signature(&edge))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn place(&self, position: usize, placed: &mut Vec<Tile>, used: &mut HashSet<TileId>) -> bool {
+        if position == self.side * self.side {
+            return true;
+        }
+
+        for id in self.candidates(position, placed) {
+            if used.contains(&id) {
+                continue;
+            }
+
+            for pixels in self.orientations[&id].iter() {
+                if !self.fits(position, pixels, placed) {
+                    continue;
+                }
+
+                placed.push(Tile {
+                    id,
+                    pixels: pixels.clone(),
+                });
+                used.insert(id);
+
+                if self.place(position + 1, placed, used) {
+                    return true;
+                }
+
+                placed.pop();
+                used.remove(&id);
+            }
+        }
+
+        false
+    }
+
+    /// Arranges all the tiles into a square, starting from a corner tile at the top-left.
+    /// Every tile is returned in the orientation it was placed in.
+    fn assemble(&self) -> Result<Vec<Tile>, String> {
+        let corners = find_corners(self.tiles, &self.index);
+        let mut placed = Vec::with_capacity(self.tiles.len());
+        let mut used = HashSet::new();
+
+        for id in corners {
+            for pixels in self.orientations[&id].iter() {
+                placed.push(Tile {
+                    id,
+                    pixels: pixels.clone(),
+                });
+                used.insert(id);
+
+                if self.place(1, &mut placed, &mut used) {
+                    return Ok(placed);
+                }
+
+                placed.pop();
+                used.remove(&id);
+            }
+        }
+
+        Err("Failed to assemble the tiles into an image".to_owned())
+    }
+}
+
+/// Strips the borders of every placed tile, and stitches the tiles together into a single image.
+fn build_image(placed: &[Tile], side: usize) -> Grid {
+    let mut image: Grid = Vec::new();
+
+    for tile_row in placed.chunks(side) {
+        let inner_size = tile_row[0].pixels.len() - 2;
+        for row in 1..=inner_size {
+            let line = tile_row
+                .iter()
+                .flat_map(|tile| tile.pixels[row][1..=inner_size].iter().cloned())
+                .collect();
+            image.push(line);
+        }
+    }
+
+    image
+}
+
+fn assemble_image(tiles: &[Tile]) -> Result<Grid, String> {
+    let assembler = Assembler::new(tiles)?;
+    let placed = assembler.assemble()?;
+    Ok(build_image(&placed, assembler.side))
+}
+
+// -- Sea Monsters --
+
+fn sea_monster_offsets() -> Vec<(usize, usize)> {
+    SEA_MONSTER
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|(_, pixel)| *pixel == PIXEL_ON)
+                .map(move |(col, _)| (row, col))
+        })
+        .collect()
+}
+
+/// Returns the image in the orientation where sea monsters were found, with every sea monster pixel marked as `O`.
+/// Returns None when there are no sea monsters in any orientation.
+fn mark_sea_monsters(image: &[Vec<char>]) -> Option<Grid> {
+    let offsets = sea_monster_offsets();
+    let monster_height = SEA_MONSTER.len();
+    let monster_width = SEA_MONSTER[0].len();
+
+    for mut candidate in orientations(image) {
+        let height = candidate.len();
+        let width = candidate[0].len();
+        if height < monster_height || width < monster_width {
+            return None;
+        }
+
+        let mut monster_pixels: HashSet<(usize, usize)> = HashSet::new();
+        for row in 0..=height - monster_height {
+            for col in 0..=width - monster_width {
+                let is_monster = offsets
+                    .iter()
+                    .all(|(r, c)| candidate[row + r][col + c] == PIXEL_ON);
+
+                if is_monster {
+                    monster_pixels.extend(offsets.iter().map(|(r, c)| (row + r, col + c)));
+                }
+            }
+        }
+
+        if !monster_pixels.is_empty() {
+            for (row, col) in monster_pixels {
+                candidate[row][col] = SEA_MONSTER_PIXEL;
+            }
+            return Some(candidate);
+        }
+    }
+
+    None
+}
+
+fn count_pixels(grid: &[Vec<char>], pixel: char) -> usize {
+    grid.iter()
+        .map(|row| row.iter().filter(|p| **p == pixel).count())
+        .sum()
+}
+
+// Part 02
+fn get_water_roughness(image: &[Vec<char>]) -> usize {
+    match mark_sea_monsters(image) {
+        Some(marked) => count_pixels(&marked, PIXEL_ON),
+        None => count_pixels(image, PIXEL_ON),
+    }
+}
+
+/// Renders a grid as text, one line per row.
+fn render(grid: &[Vec<char>]) -> String {
+    grid.iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect::<Vec<String>>();
+    let filepath = args.get(1).expect("Input filepath cannot be empty!");
+    let input =
+        fs::read_to_string(filepath).expect("Something went wrong while reading the input file");
+
+    let tiles = parse_tiles(&input).unwrap_or_else(|e| panic!("Invalid input: {}", e));
+
+    println!("-- Part 01 --");
+    println!("Product of corner tile ids: {}", get_corner_product(&tiles));
+
+    println!("-- Part 02 --");
+    let image = assemble_image(&tiles).unwrap_or_else(|e| panic!("{}", e));
+    println!("Water roughness: {}", get_water_roughness(&image));
+
+    // Optionally export the assembled image, with the sea monsters marked on it.
+    if let Some(export_path) = args.get(2) {
+        let marked = mark_sea_monsters(&image).unwrap_or(image);
+        fs::write(export_path, render(&marked) + "\n")
+            .expect("Something went wrong while exporting the image");
+        println!("Image exported to {}", export_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"
+Tile 2311:
+..##.#..#.
+##..#.....
+#...##..#.
+####.#...#
+##.##.###.
+##...#.###
+.#.#.#..##
+..#....#..
+###...#.#.
+..###..###
+
+Tile 1951:
+#.##...##.
+#.####...#
+.....#..##
+#...######
+.##.#....#
+.###.#####
+###.##.##.
+.###....#.
+..#.#..#.#
+#...##.#..
+
+Tile 1171:
+####...##.
+#..##.#..#
+##.#..#.#.
+.###.####.
+..###.####
+.##....##.
+.#...####.
+#.##.####.
+####..#...
+.....##...
+
+Tile 1427:
+###.##.#..
+.#..#.##..
+.#.##.#..#
+#.#.#.##.#
+....#...##
+...##..##.
+...#.#####
+.#.####.#.
+..#..###.#
+..##.#..#.
+
+Tile 1489:
+##.#.#....
+..##...#..
+.##..##...
+..#...#...
+#####...#.
+#..#.#.#.#
+...#.#.#..
+##.#...##.
+..##.##.##
+###.##.#..
+
+Tile 2473:
+#....####.
+#..#.##...
+#.##..#...
+######.#.#
+.#...#.#.#
+.#########
+.###.#..#.
+########.#
+##...##.#.
+..###.#.#.
+
+Tile 2971:
+..#.#....#
+#...###...
+#.#.###...
+##.##..#..
+.#####..##
+.#..####.#
+#..#.#..#.
+..####.###
+..#.#.###.
+...#.#.#.#
+
+Tile 2729:
+...#.#.#.#
+####.#....
+..#.#.....
+....#..#.#
+.##..##.#.
+.#.####...
+####.#.#..
+##.####...
+##..#.##..
+#.##...##.
+
+Tile 3079:
+#.#.#####.
+.#..######
+..#.......
+######....
+####.#..#.
+.#...#.##.
+#.#####.##
+..#.###...
+..#.......
+..#.###...
+"#;
+
+    const TILE_SIZE: usize = 10;
+    const TILES_PER_SIDE: usize = 3;
+    const INNER_SIZE: usize = TILE_SIZE - 2;
+
+    /// A tiny linear congruential generator, so that the generated puzzles are the same on every run.
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((self.0 >> 33) % bound as u64) as usize
+        }
+    }
+
+    /// Returns an image that has a single sea monster, surrounded by sparse noise.
+    fn create_image(random: &mut Random) -> Grid {
+        let size = INNER_SIZE * TILES_PER_SIDE;
+        let mut image: Grid = (0..size)
+            .map(|_| {
+                (0..size)
+                    .map(|_| if random.next(8) == 0 { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+
+        for (row, col) in sea_monster_offsets() {
+            image[row + 2][col + 1] = PIXEL_ON;
+        }
+
+        image
+    }
+
+    /// Cuts the image into tiles with matching random borders, then shuffles, rotates and flips them.
+    fn create_tiles(image: &[Vec<char>], random: &mut Random) -> Vec<(usize, Tile)> {
+        // Adjacent tiles share a row / column of this lattice as their border.
+        let lattice_size = (TILE_SIZE - 1) * TILES_PER_SIDE + 1;
+        let lattice: Grid = (0..lattice_size)
+            .map(|_| {
+                (0..lattice_size)
+                    .map(|_| if random.next(2) == 0 { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+
+        let mut tiles = Vec::new();
+        for position in 0..TILES_PER_SIDE * TILES_PER_SIDE {
+            let (tile_row, tile_col) = (position / TILES_PER_SIDE, position % TILES_PER_SIDE);
+            let mut pixels: Grid = (0..TILE_SIZE)
+                .map(|r| {
+                    let start = tile_col * (TILE_SIZE - 1);
+                    lattice[tile_row * (TILE_SIZE - 1) + r][start..start + TILE_SIZE].to_vec()
+                })
+                .collect();
+
+            for r in 0..INNER_SIZE {
+                for c in 0..INNER_SIZE {
+                    pixels[r + 1][c + 1] =
+                        image[tile_row * INNER_SIZE + r][tile_col * INNER_SIZE + c];
+                }
+            }
+
+            let orientation = random.next(8);
+            let tile = Tile {
+                id: 1000 + position as TileId * 7,
+                pixels: orientations(&pixels).swap_remove(orientation),
+            };
+            tiles.push((position, tile));
+        }
+
+        for i in (1..tiles.len()).rev() {
+            tiles.swap(i, random.next(i + 1));
+        }
+
+        tiles
+    }
+
+    #[test]
+    fn should_parse_tiles() {
+        let input = "Tile 2311:\n#.#\n..#\n##.\n\nTile 1951:\n.##\n#..\n..#\n";
+        let tiles = parse_tiles(input).unwrap();
+        assert_eq!(tiles.len(), 2);
+        assert_eq!(tiles[0].id, 2311);
+        assert_eq!(tiles[1].pixels[0], vec!['.', '#', '#']);
+
+        assert!(parse_tile("Tile x:\n#.\n.#").is_err());
+        assert!(parse_tile("Tile 1:\n#.\n.").is_err());
+    }
+
+    #[test]
+    fn should_get_all_orientations() {
+        let grid = vec![vec!['#', '.'], vec!['.', '.']];
+        let all = orientations(&grid);
+        assert_eq!(all.len(), 8);
+        assert_eq!(rotate(&rotate(&rotate(&rotate(&grid)))), grid);
+
+        // A single pixel can be in any of the 4 corners, each of which is reached twice.
+        let unique: HashSet<Grid> = all.into_iter().collect();
+        assert_eq!(unique.len(), 4);
+    }
+
+    #[test]
+    fn should_get_direction_independent_edge_signature() {
+        assert_eq!(
+            edge_signature(&['#', '.', '.']),
+            edge_signature(&['.', '.', '#'])
+        );
+        assert_eq!(edge_signature(&['#', '.', '.']), 1);
+    }
+
+    #[test]
+    fn should_find_corners_and_assemble_image() {
+        let mut random = Random(2020);
+        let image = create_image(&mut random);
+        let shuffled = create_tiles(&image, &mut random);
+
+        let corner_positions = [0, 2, 6, 8];
+        let expected_product: u64 = shuffled
+            .iter()
+            .filter(|(position, _)| corner_positions.contains(position))
+            .map(|(_, tile)| tile.id)
+            .product();
+
+        let tiles: Vec<Tile> = shuffled.into_iter().map(|(_, tile)| tile).collect();
+        assert_eq!(get_corner_product(&tiles), expected_product);
+
+        let assembled = assemble_image(&tiles).unwrap();
+        assert!(orientations(&image).contains(&assembled));
+    }
+
+    #[test]
+    fn should_get_water_roughness() {
+        let mut random = Random(25);
+        let image = create_image(&mut random);
+        let tiles: Vec<Tile> = create_tiles(&image, &mut random)
+            .into_iter()
+            .map(|(_, tile)| tile)
+            .collect();
+
+        let assembled = assemble_image(&tiles).unwrap();
+        let marked = mark_sea_monsters(&assembled).unwrap();
+
+        assert_eq!(count_pixels(&marked, SEA_MONSTER_PIXEL), 15);
+        assert_eq!(
+            get_water_roughness(&assembled),
+            count_pixels(&image, PIXEL_ON) - 15
+        );
+        assert_eq!(render(&marked).lines().count(), INNER_SIZE * TILES_PER_SIDE);
+    }
+
+    #[test]
+    fn should_solve_example() {
+        let tiles = parse_tiles(EXAMPLE).unwrap();
+        assert_eq!(get_corner_product(&tiles), 20899048083289);
+
+        let image = assemble_image(&tiles).unwrap();
+        assert_eq!(image.len(), 24);
+        assert_eq!(get_water_roughness(&image), 273);
+    }
+
+    #[test]
+    fn should_not_assemble_non_square_tile_count() {
+        let tile = Tile {
+            id: 1,
+            pixels: vec![vec!['#', '.'], vec!['.', '#']],
+        };
+        assert!(assemble_image(&[tile.clone(), tile]).is_err());
+    }
+}
@@ -1,89 +1,286 @@
-use std::{env, fs};
+use std::{collections::HashSet, env, fs};
 
 /// A Singly LinkedList of Cups, where each index holds the value to the next element, represented as a Vec.
-/// Every index points to the next cup, i.e. cups[5] returns the neighbor of cup 5.alloc
+/// Cups are stored by the rank of their label, i.e. cups[5] returns the rank of the neighbor of the 6th lowest label.
 ///
-/// For eg. The sequence `[3, 8, 9, 1, 2, 5, 4, 6, 7]` represented as a CupplyLinkedList becomes `[0, 2, 5, 8, 6, 4, 7, 3, 9, 1]`
+/// For eg. The sequence `[3, 8, 9, 1, 2, 5, 4, 6, 7]` is ranked as `[2, 7, 8, 0, 1, 4, 3, 5, 6]`,
+/// and represented as a CupplyLinkedList becomes `[1, 4, 7, 5, 3, 6, 2, 8, 0]`
+type CupplyLinkedList = Vec<u32>;
 
-type CupplyLinkedList = Vec<usize>;
+const DEFAULT_PICK_COUNT: usize = 3;
 
-fn play_game(cup_labels: &[u8], moves: u32, total_cups: usize) -> Vec<usize> {
-    // The number of values needs to be +1 of total_cups because the head of the LinkedList is 0 and acts as a dummy node.
-    let mut cups: CupplyLinkedList = vec![0usize; total_cups + 1];
-    let mut prev = cup_labels[0] as usize;
+/// How the destination cup is chosen, relative to the label of the current cup.
+/// Picked up cups are skipped, wrapping around at the lowest / highest label.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DestinationRule {
+    /// The cup with the next lower label, as in the puzzle.
+    LowerLabel,
+    /// The cup with the next higher label.
+    HigherLabel,
+}
+
+#[derive(Debug, Clone)]
+struct CupCircle {
+    cups: CupplyLinkedList,
+    /// The labels in ascending order, so that a cup's rank indexes its label
+    labels: Vec<u32>,
+    current: u32,
+    pick_count: usize,
+    destination_rule: DestinationRule,
+}
+
+impl CupCircle {
+    /// Creates a circle from the labels in clockwise order. The first label is the current cup.
+    fn new(labels: &[u32]) -> Result<Self, String> {
+        if labels.is_empty() {
+            return Err("A circle needs at least one cup".to_string());
+        }
+
+        let mut seen = HashSet::with_capacity(labels.len());
+        if let Some(label) = labels.iter().find(|&&label| !seen.insert(label)) {
+            return Err(format!("Duplicate cup label {}", label));
+        }
+
+        let mut sorted = labels.to_vec();
+        sorted.sort_unstable();
+
+        let ranks: Vec<u32> = labels
+            .iter()
+            .map(|label| sorted.binary_search(label).unwrap() as u32)
+            .collect();
+
+        // Populate the Cupply LinkedList By setting the value at rank n to point to its neighbour
+        let mut cups: CupplyLinkedList = vec![0; labels.len()];
+        for pair in ranks.windows(2) {
+            cups[pair[0] as usize] = pair[1];
+        }
+
+        // The last cup points back to the first one, closing the circle
+        cups[*ranks.last().unwrap() as usize] = ranks[0];
 
-    // Populate the Cupply LinkedList By setting the value at index n to point to its neighbour
-    for &label in cup_labels.iter().skip(1) {
-        cups[prev] = label as usize;
-        prev = label as usize;
+        Ok(CupCircle {
+            cups,
+            labels: sorted,
+            current: ranks[0],
+            pick_count: DEFAULT_PICK_COUNT.min(labels.len().saturating_sub(2)),
+            destination_rule: DestinationRule::LowerLabel,
+        })
     }
 
-    // If there are more total cups than cup labels, populate the rest of the values in the CLL
-    // This is to handle the specific usecase in Part 02
-    for value in cup_labels.len() + 1..total_cups + 1 {
-        cups[prev] = value;
-        prev = value;
+    /// Adds cups labelled from one above the highest label up to `total_cups`, after the last cup in the circle.
+    /// This is to handle the specific usecase in Part 02
+    fn extend_to(mut self, total_cups: u32) -> Self {
+        let max_label = *self.labels.last().unwrap();
+        if total_cups <= max_label {
+            return self;
+        }
+
+        // the last cup is the one that points to the current cup
+        let mut prev = self.rank_iter(self.current).last().unwrap();
+
+        // The new labels are all higher than the existing ones, so they keep the labels sorted
+        for label in max_label + 1..=total_cups {
+            let rank = self.labels.len() as u32;
+            self.labels.push(label);
+            self.cups.push(0);
+            self.cups[prev as usize] = rank;
+            prev = rank;
+        }
+        self.cups[prev as usize] = self.current;
+
+        self
     }
 
-    // Reset cups[prev] to point to first value once CLL is populated
-    cups[prev] = cup_labels[0] as usize;
+    /// Sets the number of cups picked up in every move. At least two cups have to stay in the circle.
+    fn with_pick_count(mut self, pick_count: usize) -> Result<Self, String> {
+        if pick_count + 2 > self.len() {
+            return Err(format!(
+                "Can't pick {} cups from a circle of {} cups",
+                pick_count,
+                self.len()
+            ));
+        }
 
-    // the first label is automatically designated as the current candidate
-    let mut candidate = cup_labels[0] as usize;
-    let mut picked = [0; 3];
+        self.pick_count = pick_count;
+        Ok(self)
+    }
+
+    fn with_destination_rule(mut self, destination_rule: DestinationRule) -> Self {
+        self.destination_rule = destination_rule;
+        self
+    }
+
+    fn len(&self) -> usize {
+        self.labels.len()
+    }
 
-    for _ in 0..moves {
-        let mut next_cup = cups[candidate];
-        for p in picked.iter_mut() {
-            *p = next_cup;
-            next_cup = cups[next_cup];
+    fn rank(&self, label: u32) -> Option<u32> {
+        self.labels
+            .binary_search(&label)
+            .ok()
+            .map(|rank| rank as u32)
+    }
+
+    /// Returns the label of the cup clockwise of the given cup.
+    fn next(&self, label: u32) -> Option<u32> {
+        let rank = self.rank(label)?;
+        Some(self.labels[self.cups[rank as usize] as usize])
+    }
+
+    /// Returns the rank that the destination rule moves to from `rank`, wrapping around at the ends.
+    fn step_rank(&self, rank: u32) -> u32 {
+        let max_rank = self.len() as u32 - 1;
+        match self.destination_rule {
+            DestinationRule::LowerLabel if rank == 0 => max_rank,
+            DestinationRule::LowerLabel => rank - 1,
+            DestinationRule::HigherLabel if rank == max_rank => 0,
+            DestinationRule::HigherLabel => rank + 1,
         }
+    }
 
-        // candidate becomes the cup right next to picked.
-        cups[candidate] = next_cup;
+    fn find_destination(&self, picked: &[u32]) -> u32 {
+        let mut destination = self.step_rank(self.current);
+        while picked.contains(&destination) {
+            destination = self.step_rank(destination);
+        }
 
-        let mut destination = candidate - 1;
-        while destination < 1 || picked.contains(&destination) {
-            if destination == 0 {
-                destination = total_cups;
-            } else {
-                destination -= 1;
-            }
+        destination
+    }
+
+    /// Plays a single move: picks up the cups next to the current cup, places them after the destination cup,
+    /// and moves on to the cup next to the current cup.
+    fn play_move(&mut self, picked: &mut Vec<u32>) {
+        picked.clear();
+
+        let mut next_cup = self.cups[self.current as usize];
+        for _ in 0..self.pick_count {
+            picked.push(next_cup);
+            next_cup = self.cups[next_cup as usize];
         }
 
-        // point end of picked to the cup after destination
-        cups[picked[2]] = cups[destination];
+        if let Some(&last_picked) = picked.last() {
+            // current cup becomes the cup right next to picked.
+            self.cups[self.current as usize] = next_cup;
+
+            let destination = self.find_destination(picked);
+
+            // point end of picked to the cup after destination
+            self.cups[last_picked as usize] = self.cups[destination as usize];
 
-        // point destination to the start of picked
-        cups[destination] = picked[0];
+            // point destination to the start of picked
+            self.cups[destination as usize] = picked[0];
+        }
 
         // move to the next cup
-        candidate = cups[candidate];
+        self.current = self.cups[self.current as usize];
+    }
+
+    fn play(&mut self, moves: u32) {
+        let mut picked = Vec::with_capacity(self.pick_count);
+        for _ in 0..moves {
+            self.play_move(&mut picked);
+        }
     }
 
-    cups
+    fn rank_iter(&self, rank: u32) -> CupIter<'_> {
+        CupIter {
+            circle: self,
+            next: rank,
+            remaining: self.len(),
+        }
+    }
+
+    /// Returns an iterator over the labels in the circle, going clockwise once around the circle starting at `label`.
+    fn iter_from(&self, label: u32) -> impl Iterator<Item = u32> + '_ {
+        let ranks = match self.rank(label) {
+            Some(rank) => self.rank_iter(rank),
+            None => CupIter {
+                circle: self,
+                next: 0,
+                remaining: 0,
+            },
+        };
+
+        ranks.map(move |rank| self.labels[rank as usize])
+    }
+
+    /// Returns the labels of the cups after the given cup, joined by the separator.
+    fn labels_after(&self, label: u32, separator: &str) -> String {
+        self.iter_from(label)
+            .skip(1)
+            .map(|l| l.to_string())
+            .collect::<Vec<String>>()
+            .join(separator)
+    }
 }
 
-fn get_cups(input: &str) -> Vec<u8> {
-    let labels: Vec<u8> = input
-        .chars()
-        .map(|x| x.to_digit(10).unwrap() as u8)
-        .collect();
+/// Iterates over the ranks of the cups in clockwise order.
+struct CupIter<'a> {
+    circle: &'a CupCircle,
+    next: u32,
+    remaining: usize,
+}
+
+impl<'a> Iterator for CupIter<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.remaining == 0 {
+            return None;
+        }
 
-    labels
+        let rank = self.next;
+        self.next = self.circle.cups[rank as usize];
+        self.remaining -= 1;
+        Some(rank)
+    }
 }
 
-// Part 01
-fn get_labels_after(cup_label: usize, cups: CupplyLinkedList) -> String {
-    let mut labels = String::new();
-    let mut candidate = cups[cup_label];
-    for _ in 0..8 {
-        labels.push((b'0' + candidate as u8) as char);
-        let next = cups[candidate];
-        candidate = next;
+/// Parses the cup labels. Labels are either single digits written next to each other (`389125467`),
+/// or numbers separated by commas or whitespace (`3, 18, 9`).
+fn get_cups(input: &str) -> Result<Vec<u32>, String> {
+    let input = input.trim();
+    let is_separated = input.contains(|c: char| c == ',' || c.is_whitespace());
+
+    if is_separated {
+        input
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|x| !x.is_empty())
+            .map(|x| {
+                x.parse::<u32>()
+                    .map_err(|_| format!("Invalid cup label {}", x))
+            })
+            .collect()
+    } else {
+        input
+            .chars()
+            .map(|x| x.to_digit(10).ok_or(format!("Invalid cup label {}", x)))
+            .collect()
+    }
+}
+
+/// Builds a circle with the rules passed on the command line:
+/// `day-23 <input> [pick count] [lower|higher]`
+fn create_circle(sequence: &[u32], args: &[String]) -> Result<CupCircle, String> {
+    let mut circle = CupCircle::new(sequence)?;
+
+    if let Some(pick_count) = args.get(2) {
+        let pick_count = pick_count
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid pick count {}", pick_count))?;
+        circle = circle.with_pick_count(pick_count)?;
+    }
+
+    if let Some(rule) = args.get(3) {
+        let rule = match rule.as_str() {
+            "lower" => DestinationRule::LowerLabel,
+            "higher" => DestinationRule::HigherLabel,
+            _ => return Err(format!("Invalid destination rule {}", rule)),
+        };
+        circle = circle.with_destination_rule(rule);
     }
 
-    labels
+    Ok(circle)
 }
 
 fn main() {
@@ -92,18 +289,20 @@ fn main() {
     let input =
         fs::read_to_string(filepath).expect("Something went wrong while reading the input file");
 
-    // Part 01
-    let sequence = get_cups(&input);
-    let result = play_game(&sequence, 100, sequence.len());
-    let labels = get_labels_after(1, result);
+    let sequence = get_cups(&input).unwrap_or_else(|e| panic!("Invalid input: {}", e));
 
-    println!("Labels of Cups after 1: {:?}", labels);
+    // Part 01
+    let mut circle = create_circle(&sequence, &args).unwrap_or_else(|e| panic!("{}", e));
+    let part_two_circle = circle.clone();
+    circle.play(100);
+    println!("Labels of Cups after 1: {:?}", circle.labels_after(1, ""));
 
     // Part 02
-    let cups = play_game(&sequence, 10_000_000, 1_000_000);
-    let cup1 = cups[1];
-    let cup2 = cups[cup1];
-    println!("Product of Cup1 and Cup2 {:?}", cup1 * cup2);
+    let mut circle = part_two_circle.extend_to(1_000_000);
+    circle.play(10_000_000);
+    let cup1 = circle.next(1).unwrap();
+    let cup2 = circle.next(cup1).unwrap();
+    println!("Product of Cup1 and Cup2 {:?}", cup1 as u64 * cup2 as u64);
 }
 
 #[cfg(test)]
@@ -112,9 +311,123 @@ mod tests {
 
     #[test]
     fn should_play_game() {
-        let sequence = get_cups("389125467");
-        let result = play_game(&sequence, 100, sequence.len());
-        let labels = get_labels_after(1, result);
-        assert_eq!(labels, "92658374");
+        let sequence = get_cups("389125467").unwrap();
+
+        let mut circle = CupCircle::new(&sequence).unwrap();
+        circle.play(10);
+        assert_eq!(circle.labels_after(1, ""), "92658374");
+
+        let mut circle = CupCircle::new(&sequence).unwrap();
+        circle.play(100);
+        assert_eq!(circle.labels_after(1, ""), "67384529");
+    }
+
+    #[test]
+    fn should_extend_to_million_cups() {
+        let sequence = get_cups("389125467").unwrap();
+        let mut circle = CupCircle::new(&sequence).unwrap().extend_to(1_000_000);
+        assert_eq!(circle.iter_from(3).count(), 1_000_000);
+        assert_eq!(circle.next(7), Some(10));
+        assert_eq!(circle.next(1_000_000), Some(3));
+
+        // 3 picks up 8 9 1, and places them after 2: 3 2 8 9 1 5 4 6 7 10 ...
+        circle.play(1);
+        assert_eq!(
+            circle.iter_from(3).take(10).collect::<Vec<u32>>(),
+            vec![3, 2, 8, 9, 1, 5, 4, 6, 7, 10]
+        );
+        assert_eq!(circle.next(1_000_000), Some(3));
+    }
+
+    /// Plays the full Part 02, which takes a few seconds without optimizations.
+    #[test]
+    #[ignore]
+    fn should_play_game_with_million_cups() {
+        let sequence = get_cups("389125467").unwrap();
+        let mut circle = CupCircle::new(&sequence).unwrap().extend_to(1_000_000);
+        circle.play(10_000_000);
+
+        let cup1 = circle.next(1).unwrap();
+        let cup2 = circle.next(cup1).unwrap();
+        assert_eq!((cup1, cup2), (934001, 159792));
+    }
+
+    #[test]
+    fn should_iterate_from_any_cup() {
+        let circle = CupCircle::new(&[3, 8, 9, 1, 2]).unwrap();
+        assert_eq!(
+            circle.iter_from(9).collect::<Vec<u32>>(),
+            vec![9, 1, 2, 3, 8]
+        );
+        assert_eq!(circle.iter_from(4).count(), 0);
+
+        let circle = circle.extend_to(11);
+        assert_eq!(circle.labels_after(8, ","), "9,1,2,10,11,3");
+    }
+
+    #[test]
+    fn should_play_with_multi_digit_labels() {
+        let sequence = get_cups("30, 80, 90, 10, 20, 50, 40, 60, 70").unwrap();
+        let mut circle = CupCircle::new(&sequence).unwrap();
+        circle.play(10);
+
+        // Gaps between labels are skipped, so this plays out the same as labels 1-9.
+        assert_eq!(circle.labels_after(10, " "), "90 20 60 50 80 30 70 40");
+    }
+
+    #[test]
+    fn should_play_with_sparse_labels() {
+        // Labels far apart play out the same as the labels 1-9 in the same order.
+        let labels = [
+            3,
+            4_000_000_000,
+            u32::MAX,
+            1,
+            2,
+            500_000,
+            7_000,
+            600_000_000,
+            700_000_000,
+        ];
+        let mut circle = CupCircle::new(&labels).unwrap();
+        circle.play(100);
+
+        assert_eq!(
+            circle.labels_after(1, ","),
+            "600000000,700000000,3,4000000000,7000,500000,2,4294967295"
+        );
+        assert_eq!(circle.next(1), Some(600_000_000));
+        assert_eq!(circle.next(4), None);
+        assert_eq!(circle.iter_from(u32::MAX).count(), 9);
+    }
+
+    #[test]
+    fn should_play_with_pick_count_and_destination_rule() {
+        // Picks 1 cup and places it after the next higher label:
+        // [3] 1 2 4 => 3 [2] 4 1 => [3] 4 2 1
+        let mut circle = CupCircle::new(&[3, 1, 2, 4])
+            .unwrap()
+            .with_pick_count(1)
+            .unwrap()
+            .with_destination_rule(DestinationRule::HigherLabel);
+
+        circle.play(1);
+        assert_eq!(circle.iter_from(3).collect::<Vec<u32>>(), vec![3, 2, 4, 1]);
+        circle.play(1);
+        assert_eq!(circle.iter_from(3).collect::<Vec<u32>>(), vec![3, 4, 2, 1]);
+
+        assert!(CupCircle::new(&[1, 2, 3])
+            .unwrap()
+            .with_pick_count(2)
+            .is_err());
+    }
+
+    #[test]
+    fn should_not_create_invalid_circle() {
+        assert!(CupCircle::new(&[]).is_err());
+        assert!(CupCircle::new(&[1, 2, 1]).is_err());
+        assert!(CupCircle::new(&[1, 2, 2]).is_err());
+        assert!(CupCircle::new(&[4_000_000_000, 7, 4_000_000_000]).is_err());
+        assert!(get_cups("12a").is_err());
     }
 }
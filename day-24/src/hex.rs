@@ -0,0 +1,144 @@
+use std::ops::Add;
+use std::str::FromStr;
+
+// Hexagonal grids, using cube coordinates.
+// https://www.redblobgames.com/grids/hexagons/#coordinates-cube
+
+// Every hex is a point (x, y, z) on the plane x + y + z = 0, so moving in any of the 6 directions
+// increments one of the coordinates and decrements another. With pointy-topped hexes laid out in rows,
+// east / west move along x and y, and the diagonal directions move between rows.
+
+/// One of the 6 directions from a hex to its neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    East,
+    SouthEast,
+    SouthWest,
+    West,
+    NorthWest,
+    NorthEast,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::East,
+        Direction::SouthEast,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+        Direction::NorthEast,
+    ];
+
+    /// Returns the offset to the neighbouring hex in this direction.
+    pub fn offset(self) -> Hex {
+        let (x, y, z) = match self {
+            Direction::East => (1, -1, 0),
+            Direction::SouthEast => (0, -1, 1),
+            Direction::SouthWest => (-1, 0, 1),
+            Direction::West => (-1, 1, 0),
+            Direction::NorthWest => (0, 1, -1),
+            Direction::NorthEast => (1, 0, -1),
+        };
+
+        Hex { x, y, z }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "e" => Ok(Direction::East),
+            "se" => Ok(Direction::SouthEast),
+            "sw" => Ok(Direction::SouthWest),
+            "w" => Ok(Direction::West),
+            "nw" => Ok(Direction::NorthWest),
+            "ne" => Ok(Direction::NorthEast),
+            _ => Err(format!("Invalid direction {}", s)),
+        }
+    }
+}
+
+/// A hex in cube coordinates, where `x + y + z` is always 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Hex {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Hex {
+    pub const ORIGIN: Hex = Hex { x: 0, y: 0, z: 0 };
+
+    /// Returns the hex at the given axial coordinates, i.e. the cube coordinates without `y`.
+    pub fn from_axial(q: i32, r: i32) -> Hex {
+        Hex {
+            x: q,
+            y: -q - r,
+            z: r,
+        }
+    }
+
+    pub fn neighbour(self, direction: Direction) -> Hex {
+        self + direction.offset()
+    }
+
+    /// Returns an iterator over all the 6 neighbours of the hex.
+    pub fn neighbours(self) -> impl Iterator<Item = Hex> {
+        Direction::ALL.iter().map(move |d| self.neighbour(*d))
+    }
+
+    /// Returns the number of steps needed to move from one hex to the other.
+    pub fn distance(self, other: Hex) -> u32 {
+        let (dx, dy, dz) = (self.x - other.x, self.y - other.y, self.z - other.z);
+        (dx.unsigned_abs() + dy.unsigned_abs() + dz.unsigned_abs()) / 2
+    }
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex {
+        Hex {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_have_six_distinct_neighbours_at_distance_one() {
+        let hex = Hex::from_axial(3, -2);
+        let neighbours: Vec<Hex> = hex.neighbours().collect();
+
+        assert_eq!(neighbours.len(), 6);
+        for neighbour in neighbours.iter() {
+            assert_eq!(neighbour.x + neighbour.y + neighbour.z, 0);
+            assert_eq!(hex.distance(*neighbour), 1);
+        }
+    }
+
+    #[test]
+    fn should_get_distance() {
+        let hex = Hex::ORIGIN
+            .neighbour(Direction::East)
+            .neighbour(Direction::East)
+            .neighbour(Direction::NorthEast);
+
+        assert_eq!(Hex::ORIGIN.distance(hex), 3);
+        assert_eq!(hex.distance(Hex::ORIGIN), 3);
+        assert_eq!(Hex::from_axial(-2, 2).distance(Hex::ORIGIN), 2);
+    }
+
+    #[test]
+    fn should_parse_direction() {
+        assert_eq!("nw".parse::<Direction>(), Ok(Direction::NorthWest));
+        assert!("n".parse::<Direction>().is_err());
+    }
+}
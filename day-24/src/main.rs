@@ -0,0 +1,174 @@
+use day_24::hex::{Direction, Hex};
+use std::collections::{HashMap, HashSet};
+use std::{env, fs};
+
+// Day 24: Lobby Layout
+// https://adventofcode.com/2020/day/24
+
+// Every line of the input is a path of directions (e, se, sw, w, nw, ne) without any delimiters,
+// which starts at the reference tile and ends at a tile that needs to be flipped.
+// Tiles start out white, and a tile that is flipped twice is white again.
+
+// Algorithm
+// * Parse every path into a list of directions. Since `e` and `w` are the only single letter directions,
+//   a `n` or `s` is always followed by an `e` or a `w`.
+// * Walk along every path in cube coordinates, and toggle the tile it ends on in a set of black tiles (Part 01).
+// * Every day, only black tiles and their neighbours can change colour, so counting black neighbours for just those
+//   is enough to run the cellular automaton (Part 02).
+
+type BlackTiles = HashSet<Hex>;
+
+fn parse_path(line: &str) -> Result<Vec<Direction>, String> {
+    let mut path = Vec::new();
+    let mut chars = line.trim().chars();
+
+    while let Some(c) = chars.next() {
+        let direction = match c {
+            'n' | 's' => {
+                let next = chars
+                    .next()
+                    .ok_or(format!("Incomplete direction at the end of {}", line))?;
+                format!("{}{}", c, next)
+            }
+            _ => c.to_string(),
+        };
+
+        path.push(direction.parse::<Direction>()?);
+    }
+
+    Ok(path)
+}
+
+fn follow_path(path: &[Direction]) -> Hex {
+    path.iter()
+        .fold(Hex::ORIGIN, |hex, direction| hex.neighbour(*direction))
+}
+
+// Part 01
+fn flip_tiles(input: &str) -> Result<BlackTiles, String> {
+    let mut black_tiles = BlackTiles::new();
+
+    for line in input.lines().filter(|l| !l.trim().is_empty()) {
+        let tile = follow_path(&parse_path(line)?);
+        if !black_tiles.remove(&tile) {
+            black_tiles.insert(tile);
+        }
+    }
+
+    Ok(black_tiles)
+}
+
+/// Flips the tiles for a single day.
+/// A black tile with zero or more than 2 black neighbours becomes white,
+/// and a white tile with exactly 2 black neighbours becomes black.
+fn next_day(black_tiles: &BlackTiles) -> BlackTiles {
+    let mut black_neighbours: HashMap<Hex, usize> = HashMap::new();
+    for tile in black_tiles {
+        for neighbour in tile.neighbours() {
+            *black_neighbours.entry(neighbour).or_insert(0) += 1;
+        }
+    }
+
+    black_neighbours
+        .into_iter()
+        .filter(|(tile, count)| match black_tiles.contains(tile) {
+            true => *count == 1 || *count == 2,
+            false => *count == 2,
+        })
+        .map(|(tile, _)| tile)
+        .collect()
+}
+
+// Part 02
+fn simulate(black_tiles: &BlackTiles, days: usize) -> BlackTiles {
+    (0..days).fold(black_tiles.clone(), |tiles, _| next_day(&tiles))
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect::<Vec<String>>();
+    let filepath = args.get(1).expect("Input filepath cannot be empty!");
+    let input =
+        fs::read_to_string(filepath).expect("Something went wrong while reading the input file");
+
+    let black_tiles = flip_tiles(&input).unwrap_or_else(|e| panic!("Invalid input: {}", e));
+
+    println!("-- Part 01 --");
+    println!("Number of black tiles: {}", black_tiles.len());
+
+    println!("-- Part 02 --");
+    let after = simulate(&black_tiles, 100);
+    println!("Number of black tiles after 100 days: {}", after.len());
+
+    let furthest = after
+        .iter()
+        .map(|tile| tile.distance(Hex::ORIGIN))
+        .max()
+        .unwrap_or(0);
+    println!("Furthest black tile from the reference tile: {}", furthest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = r#"sesenwnenenewseeswwswswwnenewsewsw
+neeenesenwnwwswnenewnwwsewnenwseswesw
+seswneswswsenwwnwse
+nwnwneseeswswnenewneswwnewseswneseene
+swweswneswnenwsewnwneneseenw
+eesenwseswswnenwswnwnwsewwnwsene
+sewnenenenesenwsewnenwwwse
+wenwwweseeeweswwwnwwe
+wsweesenenewnwwnwsenewsenwwsesesenwne
+neeswseenwwswnwswswnw
+nenwswwsewswnenenewsenwsenwnesesenew
+enewnwewneswsewnwswenweswnenwsenwsw
+sweneswneswneneenwnewenewwneswswnese
+swwesenesewenwneswnwwneseswwne
+enesenwswwswneneswsenwnewswseenwsese
+wnwnesenesenenwwnenwsewesewsesesew
+nenewswnwewswnenesenwnesewesw
+eneswnwswnwsenenwnwnwwseeswneewsenese
+neswnwewnwnwseenwseesewsenwsweewe
+wseweeenwnesenwwwswnew"#;
+
+    #[test]
+    fn should_parse_path() {
+        let path = parse_path("esenee").unwrap();
+        assert_eq!(
+            path,
+            vec![
+                Direction::East,
+                Direction::SouthEast,
+                Direction::NorthEast,
+                Direction::East
+            ]
+        );
+
+        assert!(parse_path("esen").is_err());
+        assert!(parse_path("exe").is_err());
+    }
+
+    #[test]
+    fn should_follow_path() {
+        assert_eq!(follow_path(&parse_path("nwwswee").unwrap()), Hex::ORIGIN);
+        assert_eq!(
+            follow_path(&parse_path("esew").unwrap()),
+            Hex::ORIGIN.neighbour(Direction::SouthEast)
+        );
+    }
+
+    #[test]
+    fn should_flip_tiles() {
+        let black_tiles = flip_tiles(INPUT).unwrap();
+        assert_eq!(black_tiles.len(), 10);
+    }
+
+    #[test]
+    fn should_simulate_days() {
+        let black_tiles = flip_tiles(INPUT).unwrap();
+        assert_eq!(simulate(&black_tiles, 1).len(), 15);
+        assert_eq!(simulate(&black_tiles, 10).len(), 37);
+        assert_eq!(simulate(&black_tiles, 100).len(), 2208);
+    }
+}
@@ -1,4 +1,6 @@
-use std::{env, fs};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fmt, fs, process};
 
 // Steps:
 // TRANSFORMATION_CONSTANT = 20201227
@@ -6,61 +8,364 @@ use std::{env, fs};
 // Transform a Subject Number (7) => loop x times => x*=subject. x = x % TRANFORMATOIN_CONSTANT
 // find loop size
 
-const TRANSFORMATION_CONSTANT: usize = 20201227;
-type LoopSize = usize;
-type EncryptionKey = usize;
+// Transforming a subject number is modular exponentiation: subject ^ loop_size % modulus,
+// which can be done in O(log loop_size) steps by squaring.
+// Finding the loop size from a public key is the discrete logarithm, which is solved using baby-step giant-step:
+// * loop_size = i * m + j, where m = ceil(sqrt(modulus)) and 0 <= i, j < m
+// * Baby steps: store subject ^ j for every j.
+// * Giant steps: public_key * subject ^ (-m * i) == subject ^ j, for some j in the stored baby steps.
+// This takes O(sqrt(modulus)) time and memory, instead of O(loop_size).
+// The baby steps are capped at MAX_BABY_STEPS, so moduli up to MAX_BABY_STEPS ^ 2 can be cracked.
 
-fn transform_subject_number(subject_number: usize, loop_size: usize) -> usize {
-    let mut value: usize = 1;
-    for _ in 0..loop_size {
-        value *= subject_number;
-        value %= TRANSFORMATION_CONSTANT;
+const TRANSFORMATION_CONSTANT: u64 = 20201227;
+const SUBJECT_NUMBER: u64 = 7;
+const MAX_BABY_STEPS: u64 = 1 << 22;
+
+type LoopSize = u64;
+type EncryptionKey = u64;
+
+fn mod_mul(a: u64, b: u64, modulus: u64) -> u64 {
+    ((a as u128 * b as u128) % modulus as u128) as u64
+}
+
+/// Returns base ^ exponent % modulus, using exponentiation by squaring.
+fn mod_pow(base: u64, exponent: u64, modulus: u64) -> u64 {
+    let mut result = 1 % modulus;
+    let mut base = base % modulus;
+    let mut exponent = exponent;
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mod_mul(result, base, modulus);
+        }
+        base = mod_mul(base, base, modulus);
+        exponent >>= 1;
     }
 
-    value
+    result
 }
 
-fn get_loop_size(subject: usize, candidate_public_key: usize) -> LoopSize {
+/// Deterministic Miller-Rabin: these bases are enough to tell apart every prime and composite below 2 ^ 64.
+fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(base) = BASES.iter().find(|&&base| n.is_multiple_of(base)) {
+        return n == *base;
+    }
+
+    // n - 1 = d * 2 ^ s, with d odd
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+
+    BASES.iter().all(|&base| {
+        let mut x = mod_pow(base, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = mod_mul(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+fn transform_subject_number(subject_number: u64, loop_size: LoopSize, modulus: u64) -> u64 {
+    mod_pow(subject_number, loop_size, modulus)
+}
+
+/// Returns the smallest loop size that transforms the subject into the public key.
+/// The modulus needs to be a prime, as the inverse of the subject is found using Fermat's little theorem.
+fn get_loop_size(
+    subject: u64,
+    candidate_public_key: u64,
+    modulus: u64,
+) -> Result<LoopSize, HandshakeError> {
+    let not_found = HandshakeError::LoopSizeNotFound {
+        public_key: candidate_public_key,
+    };
+    let subject = subject % modulus;
+    let candidate_public_key = candidate_public_key % modulus;
+    if subject == 0 {
+        return Err(not_found);
+    }
+
+    let m = (modulus as f64).sqrt().ceil() as u64;
+    if m > MAX_BABY_STEPS {
+        return Err(HandshakeError::ModulusTooLarge { modulus });
+    }
+
+    // Baby steps: subject ^ j => j, keeping the smallest j for every value.
+    let mut baby_steps: HashMap<u64, u64> = HashMap::with_capacity(m as usize);
     let mut value = 1;
-    let mut loop_size: LoopSize = 1;
+    for j in 0..m {
+        baby_steps.entry(value).or_insert(j);
+        value = mod_mul(value, subject, modulus);
+    }
+
+    // subject ^ (-m) == subject ^ (modulus - 1 - m), since subject ^ (modulus - 1) == 1
+    let giant_step = mod_pow(subject, (modulus - 1) - m % (modulus - 1), modulus);
+
+    let mut value = candidate_public_key;
+    for i in 0..m {
+        if let Some(j) = baby_steps.get(&value) {
+            return Ok(i * m + j);
+        }
+        value = mod_mul(value, giant_step, modulus);
+    }
+
+    Err(not_found)
+}
+
+/// The parameters both devices agree on: every public key is the subject number transformed modulo the modulus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Protocol {
+    subject_number: u64,
+    modulus: u64,
+}
 
-    loop {
-        value *= subject;
-        value %= TRANSFORMATION_CONSTANT;
-        if value == candidate_public_key {
-            break loop_size;
+impl Protocol {
+    /// Creates a protocol, checking that the modulus is a prime, which cracking the loop sizes relies on.
+    fn new(subject_number: u64, modulus: u64) -> Result<Self, String> {
+        if !is_prime(modulus) {
+            return Err(format!("Modulus should be a prime, got {}", modulus));
+        }
+
+        Ok(Protocol {
+            subject_number,
+            modulus,
+        })
+    }
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Protocol {
+            subject_number: SUBJECT_NUMBER,
+            modulus: TRANSFORMATION_CONSTANT,
         }
-        loop_size += 1;
     }
 }
 
-fn find_encryption_key(card_public_key: usize, door_public_key: usize) -> EncryptionKey {
-    let card_loop_size = get_loop_size(7, card_public_key);
-    let door_loop_size = get_loop_size(7, door_public_key);
+/// A card or a door, which only knows its own secret loop size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Device {
+    loop_size: LoopSize,
+}
 
-    let key1 = transform_subject_number(door_public_key, card_loop_size);
-    let key2 = transform_subject_number(card_public_key, door_loop_size);
+impl Device {
+    fn public_key(&self, protocol: &Protocol) -> u64 {
+        transform_subject_number(protocol.subject_number, self.loop_size, protocol.modulus)
+    }
 
-    if key1 == key2 {
-        return key1;
+    /// Derives the encryption key from the public key received from the other device.
+    fn encryption_key(&self, other_public_key: u64, protocol: &Protocol) -> EncryptionKey {
+        transform_subject_number(other_public_key, self.loop_size, protocol.modulus)
     }
+}
 
-    panic!("Encryption key's dont match. Failed to unlock the door");
+#[derive(Debug, PartialEq)]
+enum HandshakeError {
+    /// No loop size transforms the subject number into the public key.
+    LoopSizeNotFound { public_key: u64 },
+    /// The modulus needs more baby steps than `MAX_BABY_STEPS` to find loop sizes.
+    ModulusTooLarge { modulus: u64 },
+    /// The card and the door derived different encryption keys.
+    KeyMismatch {
+        card_key: EncryptionKey,
+        door_key: EncryptionKey,
+    },
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HandshakeError::LoopSizeNotFound { public_key } => {
+                write!(
+                    f,
+                    "Failed to find the loop size for public key {}",
+                    public_key
+                )
+            }
+            HandshakeError::ModulusTooLarge { modulus } => write!(
+                f,
+                "Modulus {} is too large to find loop sizes for, at most {} is supported",
+                modulus,
+                MAX_BABY_STEPS * MAX_BABY_STEPS
+            ),
+            HandshakeError::KeyMismatch { card_key, door_key } => write!(
+                f,
+                "Encryption keys don't match (card: {}, door: {}). Failed to unlock the door",
+                card_key, door_key
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Handshake {
+    protocol: Protocol,
+    card: Device,
+    door: Device,
+}
+
+impl Handshake {
+    fn new(protocol: Protocol, card_loop_size: LoopSize, door_loop_size: LoopSize) -> Self {
+        Handshake {
+            protocol,
+            card: Device {
+                loop_size: card_loop_size,
+            },
+            door: Device {
+                loop_size: door_loop_size,
+            },
+        }
+    }
+
+    /// Recovers the secret loop sizes of both devices from their public keys.
+    fn crack(
+        protocol: Protocol,
+        card_public_key: u64,
+        door_public_key: u64,
+    ) -> Result<Self, HandshakeError> {
+        let find_loop_size =
+            |public_key: u64| get_loop_size(protocol.subject_number, public_key, protocol.modulus);
+
+        Ok(Handshake::new(
+            protocol,
+            find_loop_size(card_public_key)?,
+            find_loop_size(door_public_key)?,
+        ))
+    }
+
+    /// Returns the public keys of the card and the door.
+    fn public_keys(&self) -> (u64, u64) {
+        (
+            self.card.public_key(&self.protocol),
+            self.door.public_key(&self.protocol),
+        )
+    }
+
+    /// Derives the encryption key on both devices, given the public keys each of them received.
+    /// The card receives the door's public key and vice versa.
+    fn derive_key(
+        &self,
+        card_public_key: u64,
+        door_public_key: u64,
+    ) -> Result<EncryptionKey, HandshakeError> {
+        let card_key = self.card.encryption_key(door_public_key, &self.protocol);
+        let door_key = self.door.encryption_key(card_public_key, &self.protocol);
+
+        if card_key != door_key {
+            return Err(HandshakeError::KeyMismatch { card_key, door_key });
+        }
+
+        Ok(card_key)
+    }
+
+    /// Exchanges the public keys between the card and the door, and derives the shared encryption key.
+    fn exchange(&self) -> Result<EncryptionKey, HandshakeError> {
+        let (card_public_key, door_public_key) = self.public_keys();
+        self.derive_key(card_public_key, door_public_key)
+    }
+}
+
+fn find_encryption_key(
+    card_public_key: u64,
+    door_public_key: u64,
+    protocol: Protocol,
+) -> Result<EncryptionKey, HandshakeError> {
+    Handshake::crack(protocol, card_public_key, door_public_key)?
+        .derive_key(card_public_key, door_public_key)
+}
+
+/// A xorshift generator, seeded from the clock. Good enough to pick secret loop sizes for test keys.
+fn random_loop_size(state: &mut u64, modulus: u64) -> LoopSize {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+
+    // Loop sizes are in 1..modulus - 1, since subject ^ (modulus - 1) == 1.
+    1 + *state % modulus.saturating_sub(2).max(1)
+}
+
+fn generate(protocol: Protocol) {
+    let mut state = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(1)
+        | 1;
+
+    let handshake = Handshake::new(
+        protocol,
+        random_loop_size(&mut state, protocol.modulus),
+        random_loop_size(&mut state, protocol.modulus),
+    );
+    let (card_public_key, door_public_key) = handshake.public_keys();
+
+    println!(
+        "Card: loop size {}, public key {}",
+        handshake.card.loop_size, card_public_key
+    );
+    println!(
+        "Door: loop size {}, public key {}",
+        handshake.door.loop_size, door_public_key
+    );
+
+    match handshake.exchange() {
+        Ok(key) => println!("Encryption Key: {}", key),
+        Err(e) => eprintln!("Error: {}", e),
+    }
+}
+
+fn parse_number(value: Option<&String>, name: &str) -> Option<u64> {
+    value.map(|v| {
+        v.parse::<u64>()
+            .unwrap_or_else(|_| panic!("{} should be a number", name))
+    })
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect::<Vec<String>>();
+
+    // The modulus and the subject can optionally be passed in after the input (or `generate`),
+    // to use a protocol other than the default one.
+    let default = Protocol::default();
+    let protocol = Protocol::new(
+        parse_number(args.get(3), "Subject").unwrap_or(default.subject_number),
+        parse_number(args.get(2), "Modulus").unwrap_or(default.modulus),
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    });
+
+    // Generate test key pairs with: day-25 generate [modulus] [subject]
+    if args.get(1).map(|a| a.as_str()) == Some("generate") {
+        generate(protocol);
+        return;
+    }
+
     let filepath = args.get(1).expect("Input filepath cannot be empty!");
     let input =
         fs::read_to_string(filepath).expect("Something went wrong while reading the input file");
 
-    let public_keys: Vec<usize> = input
-        .split('\n')
-        .map(|x| x.parse::<usize>().unwrap())
+    let public_keys: Vec<u64> = input
+        .split_whitespace()
+        .map(|x| x.parse::<u64>().unwrap())
         .collect();
 
-    let encryption_key = find_encryption_key(public_keys[0], public_keys[1]);
-    println!("Encryption Key: {:?}", encryption_key);
+    match find_encryption_key(public_keys[0], public_keys[1], protocol) {
+        Ok(encryption_key) => println!("Encryption Key: {:?}", encryption_key),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -69,14 +374,136 @@ mod tests {
 
     #[test]
     fn should_get_loop_size() {
-        let loop_size = get_loop_size(7, 5764801);
-        assert_eq!(loop_size, 8);
-        let loop_size = get_loop_size(7, 17807724);
-        assert_eq!(loop_size, 11);
+        let loop_size = get_loop_size(7, 5764801, TRANSFORMATION_CONSTANT);
+        assert_eq!(loop_size, Ok(8));
+        let loop_size = get_loop_size(7, 17807724, TRANSFORMATION_CONSTANT);
+        assert_eq!(loop_size, Ok(11));
     }
 
     #[test]
     fn should_find_encryption_key() {
-        assert_eq!(find_encryption_key(5764801, 17807724), 14897079);
+        assert_eq!(
+            find_encryption_key(5764801, 17807724, Protocol::default()),
+            Ok(14897079)
+        );
+    }
+
+    #[test]
+    fn should_transform_subject_number() {
+        assert_eq!(
+            transform_subject_number(7, 8, TRANSFORMATION_CONSTANT),
+            5764801
+        );
+        assert_eq!(
+            transform_subject_number(17807724, 8, TRANSFORMATION_CONSTANT),
+            14897079
+        );
+        assert_eq!(mod_pow(3, 0, 7), 1);
+        assert_eq!(mod_pow(5, 3, 1), 0);
+    }
+
+    #[test]
+    fn should_get_loop_size_for_large_prime_modulus() {
+        let modulus = 1_000_000_007;
+        let loop_size = 987_654_321;
+        let public_key = transform_subject_number(5, loop_size, modulus);
+
+        // 5 is a primitive root of 1_000_000_007, so the loop size is unique.
+        assert_eq!(get_loop_size(5, public_key, modulus), Ok(loop_size));
+    }
+
+    #[test]
+    fn should_not_get_loop_size_for_unreachable_key() {
+        // 2 only generates the quadratic residues {1, 2, 4} modulo 7.
+        assert_eq!(
+            get_loop_size(2, 3, 7),
+            Err(HandshakeError::LoopSizeNotFound { public_key: 3 })
+        );
+    }
+
+    #[test]
+    fn should_exchange_keys_in_handshake() {
+        let handshake = Handshake::new(Protocol::default(), 8, 11);
+        assert_eq!(handshake.public_keys(), (5764801, 17807724));
+        assert_eq!(handshake.exchange(), Ok(14897079));
+
+        let cracked = Handshake::crack(Protocol::default(), 5764801, 17807724).unwrap();
+        assert_eq!(cracked, handshake);
+    }
+
+    #[test]
+    fn should_exchange_keys_with_custom_protocol() {
+        let protocol = Protocol::new(5, 1_000_000_007).unwrap();
+        let handshake = Handshake::new(protocol, 123_456_789, 987_654_321);
+        let (card_public_key, door_public_key) = handshake.public_keys();
+
+        let key = handshake.exchange().unwrap();
+        assert_eq!(
+            find_encryption_key(card_public_key, door_public_key, protocol),
+            Ok(key)
+        );
+    }
+
+    #[test]
+    fn should_not_crack_handshake_with_too_large_modulus() {
+        let modulus = 18_446_744_073_709_551_557;
+        let protocol = Protocol::new(2, modulus).unwrap();
+        let handshake = Handshake::new(protocol, 123_456_789, 987_654_321);
+        let (card_public_key, door_public_key) = handshake.public_keys();
+
+        // The handshake itself works, but cracking it would need 2 ^ 32 baby steps.
+        assert!(handshake.exchange().is_ok());
+        assert_eq!(
+            Handshake::crack(protocol, card_public_key, door_public_key),
+            Err(HandshakeError::ModulusTooLarge { modulus })
+        );
+    }
+
+    #[test]
+    fn should_fail_handshake_when_keys_dont_match() {
+        let handshake = Handshake::new(Protocol::default(), 8, 11);
+
+        // The door receives a tampered public key instead of the card's.
+        let result = handshake.derive_key(5764802, 17807724);
+        assert!(matches!(result, Err(HandshakeError::KeyMismatch { .. })));
+
+        let protocol = Protocol::new(2, 7).unwrap();
+        assert_eq!(
+            find_encryption_key(3, 2, protocol),
+            Err(HandshakeError::LoopSizeNotFound { public_key: 3 })
+        );
+    }
+
+    #[test]
+    fn should_only_create_protocol_with_prime_modulus() {
+        assert_eq!(
+            Protocol::new(SUBJECT_NUMBER, TRANSFORMATION_CONSTANT),
+            Ok(Protocol::default())
+        );
+        assert!(Protocol::new(7, 2).is_ok());
+        assert!(Protocol::new(7, 0).is_err());
+        assert!(Protocol::new(7, 1).is_err());
+        assert!(Protocol::new(7, 20201225).is_err());
+        // 3215031751 = 151 * 751 * 28351 fools Miller-Rabin with the bases 2, 3, 5 and 7.
+        assert!(Protocol::new(7, 3_215_031_751).is_err());
+        assert!(Protocol::new(7, 18_446_744_073_709_551_557).is_ok());
+        assert!(Protocol::new(7, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn should_find_primes() {
+        let primes: Vec<u64> = (0..30).filter(|n| is_prime(*n)).collect();
+        assert_eq!(primes, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert!(is_prime(1_000_000_007));
+        assert!(!is_prime(1_000_000_007 * 3));
+    }
+
+    #[test]
+    fn should_generate_loop_sizes_in_range() {
+        let mut state = 42;
+        for _ in 0..1000 {
+            let loop_size = random_loop_size(&mut state, 11);
+            assert!((1..10).contains(&loop_size));
+        }
     }
 }